@@ -0,0 +1,30 @@
+// Bulk generation throughput, as `pw -n N -q` writes it: `cargo bench`.
+
+use std::io;
+use std::time::Instant;
+
+use pw::batch::Batch;
+use pw::{Generator, OsSource};
+
+const COUNT: u64 = 200_000;
+
+fn main() {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    for name in ["strong", "memorable", "secret", "uuid"] {
+        let profile = pw::find_profile(name).expect("built-in profile");
+        let mut counts = vec![1, threads];
+        counts.dedup();
+        for threads in counts {
+            let mut batch = Batch::new(&profile as &dyn Generator);
+            batch.count = Some(COUNT);
+            batch.threads = threads;
+            let start = Instant::now();
+            let summary = batch.write(&mut OsSource, io::sink()).expect("batch");
+            let seconds = start.elapsed().as_secs_f64();
+            println!(
+                "{name:<10} {threads:>2} thread(s): {:>10.0} secrets/s",
+                summary.written as f64 / seconds
+            );
+        }
+    }
+}
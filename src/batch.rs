@@ -0,0 +1,518 @@
+use std::collections::HashSet;
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, BufWriter, ErrorKind, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::SeedableRng;
+use zeroize::Zeroizing;
+
+use crate::error::{Error, Result};
+use crate::generator::Generator;
+use crate::policy::Policy;
+use crate::rng::RandomSource;
+
+/// Secrets a worker generates before handing them to the writer.
+const CHUNK: u64 = 1024;
+/// Output buffer size, large enough that a write is rarely a system call
+/// per secret.
+const BUFFER: usize = 64 * 1024;
+/// Duplicates in a row after which a unique batch gives up. Batches are at
+/// most half their output space, so this only happens when a policy or a
+/// generator without known entropy leaves far fewer secrets than expected.
+const MAX_DUPLICATES: u32 = 1000;
+
+/// Writes one secret and its line ending.
+pub type Format<'a> = &'a (dyn Fn(&mut dyn Write, &str) -> io::Result<()> + Sync);
+
+/// One secret per line, as `pw -q` prints them.
+pub fn line(out: &mut dyn Write, secret: &str) -> io::Result<()> {
+    writeln!(out, "{secret}")
+}
+
+/// A batch of secrets from one generator, written through a single buffered
+/// writer.
+pub struct Batch<'a> {
+    pub generator: &'a dyn Generator,
+    /// Number of secrets, or `None` to write until the output is closed.
+    pub count: Option<u64>,
+    /// With more than one thread, each worker draws from its own ChaCha20
+    /// keyed from the batch's random source, and secrets come out in no
+    /// particular order.
+    pub threads: usize,
+    /// Only secrets compliant with this policy are written.
+    pub policy: Option<&'a Policy>,
+    /// Never write the same secret twice. The count must be at most half
+    /// the generator's output space, so that duplicates stay rare.
+    pub unique: bool,
+    pub format: Format<'a>,
+    /// Written between two secrets.
+    pub separator: &'a [u8],
+    /// Written before the first secret, and after the last one unless the
+    /// reader went away.
+    pub header: &'a [u8],
+    pub footer: &'a [u8],
+}
+
+/// What a batch wrote.
+pub struct Summary {
+    pub written: u64,
+    /// The last secret written.
+    pub last: Option<Zeroizing<String>>,
+    /// The reader went away (`EPIPE`) before the batch was complete.
+    pub closed: bool,
+    /// Duplicates drawn and discarded by a unique batch.
+    pub rejected: u64,
+}
+
+/// Fingerprints of the secrets of a unique batch. Only keyed 64-bit hashes
+/// are kept, so the secrets do not linger in memory; a collision can reject
+/// a distinct secret but never let a duplicate through.
+#[derive(Default)]
+struct Seen {
+    hasher: RandomState,
+    fingerprints: Mutex<HashSet<u64>>,
+    rejected: AtomicU64,
+}
+
+impl Seen {
+    /// Records `secret`, or counts a rejection if it was already written.
+    fn insert(&self, secret: &str) -> bool {
+        let fingerprint = self.hasher.hash_one(secret);
+        let new = self
+            .fingerprints
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(fingerprint);
+        if !new {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        new
+    }
+}
+
+/// Secrets generated by a worker, already formatted.
+struct Chunk {
+    text: Zeroizing<Vec<u8>>,
+    items: u64,
+    last: Zeroizing<String>,
+}
+
+impl<'a> Batch<'a> {
+    /// A single secret from `generator`, one per line.
+    pub fn new(generator: &'a dyn Generator) -> Self {
+        Self {
+            generator,
+            count: Some(1),
+            threads: 1,
+            policy: None,
+            unique: false,
+            format: &line,
+            separator: b"",
+            header: b"",
+            footer: b"",
+        }
+    }
+
+    /// Generates the batch into `out`, drawing from `rng`. A closed pipe ends
+    /// the batch early without an error.
+    pub fn write(&self, rng: &mut dyn RandomSource, out: impl Write) -> Result<Summary> {
+        let seen = if self.unique {
+            self.check_space()?;
+            Some(Seen::default())
+        } else {
+            None
+        };
+        let seen = seen.as_ref();
+        let mut out = BufWriter::with_capacity(BUFFER, out);
+        let mut summary = Summary {
+            written: 0,
+            last: None,
+            closed: false,
+            rejected: 0,
+        };
+        // No more workers than chunks, so small batches stay on this thread
+        let chunks = self.count.map_or(u64::MAX, |count| count.div_ceil(CHUNK));
+        let result = out.write_all(self.header).and_then(|()| {
+            if self.threads > 1 && chunks > 1 {
+                self.write_parallel(rng, &mut out, &mut summary, chunks, seen)
+            } else {
+                self.write_sequential(rng, &mut out, &mut summary, seen)
+            }
+        });
+        summary.rejected = seen.map_or(0, |seen| seen.rejected.load(Ordering::Relaxed));
+        match result
+            .and_then(|()| out.write_all(self.footer))
+            .and_then(|()| out.flush())
+        {
+            Ok(()) => Ok(summary),
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                summary.closed = true;
+                Ok(summary)
+            }
+            Err(e) => Err(output_error(e)),
+        }
+    }
+
+    /// Refuses unbounded unique batches, and those larger than half the
+    /// output space, where duplicates would be drawn about as often as new
+    /// secrets.
+    fn check_space(&self) -> Result<()> {
+        let Some(count) = self.count else {
+            return Err(Error::InvalidCount);
+        };
+        let Some(bits) = self.generator.entropy() else {
+            return Ok(());
+        };
+        // Rounded, as entropies such as log2(10) * 4 do not convert back exactly
+        let space = bits.exp2().round();
+        if count as f64 > space / 2.0 {
+            let space = if space < 1e15 {
+                format!("{space:.0}")
+            } else {
+                format!("2^{bits:.1}")
+            };
+            return Err(Error::NotUnique(format!(
+                "{count} requested from {space} possible, at most half can be generated"
+            )));
+        }
+        Ok(())
+    }
+
+    fn generate(
+        &self,
+        rng: &mut dyn RandomSource,
+        seen: Option<&Seen>,
+    ) -> Result<Zeroizing<String>> {
+        for _ in 0..MAX_DUPLICATES {
+            let secret = Zeroizing::new(match self.policy {
+                Some(policy) => policy.enforce(|| self.generator.generate(rng))?,
+                None => self.generator.generate(rng)?,
+            });
+            if seen.is_none_or(|seen| seen.insert(&secret)) {
+                return Ok(secret);
+            }
+        }
+        Err(Error::NotUnique(format!(
+            "{MAX_DUPLICATES} duplicates in a row, too few distinct secrets are left"
+        )))
+    }
+
+    fn write_sequential(
+        &self,
+        rng: &mut dyn RandomSource,
+        out: &mut dyn Write,
+        summary: &mut Summary,
+        seen: Option<&Seen>,
+    ) -> io::Result<()> {
+        while self.count.is_none_or(|count| summary.written < count) {
+            let secret = self.generate(rng, seen).map_err(generation_error)?;
+            if summary.written > 0 {
+                out.write_all(self.separator)?;
+            }
+            (self.format)(out, &secret)?;
+            summary.written += 1;
+            summary.last = Some(secret);
+        }
+        Ok(())
+    }
+
+    fn write_parallel(
+        &self,
+        rng: &mut dyn RandomSource,
+        out: &mut dyn Write,
+        summary: &mut Summary,
+        chunks: u64,
+        seen: Option<&Seen>,
+    ) -> io::Result<()> {
+        let threads = self.threads.min(chunks.try_into().unwrap_or(usize::MAX));
+        let mut seeds = Vec::with_capacity(threads);
+        for _ in 0..threads {
+            let mut seed = Zeroizing::new([0u8; 32]);
+            rng.fill(seed.as_mut()).map_err(generation_error)?;
+            seeds.push(seed);
+        }
+        let remaining = AtomicU64::new(self.count.unwrap_or(u64::MAX));
+        let stop = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::sync_channel::<Result<Chunk>>(2 * threads);
+
+        thread::scope(|scope| {
+            for seed in seeds {
+                let sender = sender.clone();
+                let (remaining, stop) = (&remaining, &stop);
+                scope.spawn(move || {
+                    let mut rng = ChaCha20Rng::from_seed(*seed);
+                    while !stop.load(Ordering::Relaxed) {
+                        let items = claim(remaining, self.count.is_none());
+                        if items == 0 {
+                            break;
+                        }
+                        let chunk = self.chunk(&mut rng, items, seen);
+                        let failed = chunk.is_err();
+                        if sender.send(chunk).is_err() || failed {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // Dropping the receiver on the way out unblocks the workers
+            let result = receiver.into_iter().try_for_each(|chunk| {
+                let chunk = chunk.map_err(generation_error)?;
+                if summary.written > 0 {
+                    out.write_all(self.separator)?;
+                }
+                out.write_all(&chunk.text)?;
+                summary.written += chunk.items;
+                summary.last = Some(chunk.last);
+                Ok(())
+            });
+            stop.store(true, Ordering::Relaxed);
+            result
+        })
+    }
+
+    fn chunk(&self, rng: &mut ChaCha20Rng, items: u64, seen: Option<&Seen>) -> Result<Chunk> {
+        let mut text = Zeroizing::new(Vec::new());
+        let mut last = Zeroizing::new(String::new());
+        for i in 0..items {
+            last = self.generate(rng, seen)?;
+            if i > 0 {
+                text.extend_from_slice(self.separator);
+            }
+            // Only fails for formats that write elsewhere than `text`
+            (self.format)(&mut *text, &last).map_err(|e| Error::Output(e.to_string()))?;
+        }
+        Ok(Chunk { text, items, last })
+    }
+}
+
+/// Takes up to [`CHUNK`] secrets from the shared count.
+fn claim(remaining: &AtomicU64, unbounded: bool) -> u64 {
+    if unbounded {
+        return CHUNK;
+    }
+    remaining
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+            Some(n - n.min(CHUNK))
+        })
+        .map_or(0, |n| n.min(CHUNK))
+}
+
+/// Carries a generation failure through the writer's `io::Result`s.
+fn generation_error(e: Error) -> io::Error {
+    io::Error::other(e)
+}
+
+fn output_error(e: io::Error) -> Error {
+    match e.get_ref().and_then(|inner| inner.downcast_ref::<Error>()) {
+        Some(e) => e.clone(),
+        None => Error::Output(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::token::TokenOptions;
+
+    /// Accepts `capacity` bytes, then fails like a pipe whose reader exited.
+    struct Pipe {
+        written: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let room = self.capacity - self.written.len();
+            if room == 0 {
+                return Err(ErrorKind::BrokenPipe.into());
+            }
+            let n = buf.len().min(room);
+            self.written.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn token() -> TokenOptions {
+        TokenOptions {
+            bytes: 8,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn writes_count_lines() {
+        let token = token();
+        let mut batch = Batch::new(&token);
+        batch.count = Some(5);
+        let mut out = Vec::new();
+        let summary = batch.write(&mut rand::rng(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(summary.written, 5);
+        assert_eq!(text.lines().count(), 5);
+        assert_eq!(
+            text.lines().last(),
+            summary.last.as_deref().map(String::as_str)
+        );
+        assert!(!summary.closed);
+    }
+
+    #[test]
+    fn separator_between_secrets() {
+        let token = token();
+        let mut batch = Batch::new(&token);
+        batch.count = Some(3);
+        batch.separator = b"\n";
+        let mut out = Vec::new();
+        batch.write(&mut rand::rng(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.matches("\n\n").count(), 2);
+        assert!(!text.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn header_and_footer() {
+        let token = token();
+        let mut batch = Batch::new(&token);
+        batch.count = Some(2);
+        batch.header = b"[";
+        batch.separator = b",";
+        batch.footer = b"]";
+        let mut out = Vec::new();
+        batch.write(&mut rand::rng(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with('[') && text.ends_with("\n]"));
+        assert_eq!(text.matches("\n,").count(), 1);
+    }
+
+    #[test]
+    fn threads_write_whole_count() {
+        let token = token();
+        let mut batch = Batch::new(&token);
+        batch.count = Some(3 * CHUNK + 7);
+        batch.threads = 4;
+        let mut out = Vec::new();
+        let summary = batch.write(&mut rand::rng(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(summary.written, 3 * CHUNK + 7);
+        let mut lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len() as u64, 3 * CHUNK + 7);
+        lines.sort_unstable();
+        lines.dedup();
+        assert_eq!(lines.len() as u64, 3 * CHUNK + 7);
+    }
+
+    #[test]
+    fn unbounded_stops_at_closed_pipe() {
+        let token = token();
+        for threads in [1, 3] {
+            let mut batch = Batch::new(&token);
+            batch.count = None;
+            batch.threads = threads;
+            let mut pipe = Pipe {
+                written: Vec::new(),
+                capacity: 1 << 20,
+            };
+            let summary = batch.write(&mut rand::rng(), &mut pipe).unwrap();
+            assert!(summary.closed);
+            assert_eq!(pipe.written.len(), 1 << 20);
+        }
+    }
+
+    /// Two secrets, with no known entropy.
+    struct Coin;
+
+    impl Generator for Coin {
+        fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+            Ok(crate::rng::below(rng, 2)?.to_string())
+        }
+
+        fn describe(&self) -> String {
+            "0 or 1".to_string()
+        }
+
+        fn kind(&self) -> &'static str {
+            "coin"
+        }
+
+        fn entropy(&self) -> Option<f64> {
+            None
+        }
+
+        fn parameters(&self) -> Vec<(&'static str, String)> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn unique_batches_are_distinct() {
+        let pin = crate::find_profile("pin").unwrap();
+        for threads in [1, 4] {
+            let mut batch = Batch::new(&pin);
+            batch.count = Some(5000);
+            batch.threads = threads;
+            batch.unique = true;
+            let mut out = Vec::new();
+            let summary = batch.write(&mut rand::rng(), &mut out).unwrap();
+            let text = String::from_utf8(out).unwrap();
+            let distinct: HashSet<&str> = text.lines().collect();
+            assert_eq!(distinct.len(), 5000);
+            assert_eq!(summary.written, 5000);
+            assert!(summary.rejected > 0);
+        }
+    }
+
+    #[test]
+    fn unique_batches_fit_the_space() {
+        let pin = crate::find_profile("pin").unwrap();
+        let mut batch = Batch::new(&pin);
+        batch.unique = true;
+        batch.count = Some(5001);
+        assert!(matches!(
+            batch.write(&mut rand::rng(), io::sink()),
+            Err(Error::NotUnique(_))
+        ));
+        batch.count = None;
+        assert_eq!(
+            batch.write(&mut rand::rng(), io::sink()).err(),
+            Some(Error::InvalidCount)
+        );
+
+        // Without a known space, the batch stops once duplicates keep coming
+        let mut batch = Batch::new(&Coin);
+        batch.unique = true;
+        batch.count = Some(3);
+        let mut out = Vec::new();
+        assert!(matches!(
+            batch.write(&mut rand::rng(), &mut out),
+            Err(Error::NotUnique(_))
+        ));
+    }
+
+    #[test]
+    fn generation_errors_are_reported() {
+        let policy = Policy {
+            min_length: Some(100),
+            ..Default::default()
+        };
+        let token = token();
+        for threads in [1, 2] {
+            let mut batch = Batch::new(&token);
+            batch.count = Some(2 * CHUNK);
+            batch.threads = threads;
+            batch.policy = Some(&policy);
+            assert!(matches!(
+                batch.write(&mut rand::rng(), io::sink()),
+                Err(Error::PolicyViolation(_))
+            ));
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "pw", version, about = "A fast, secure password generator")]
@@ -10,21 +12,8 @@ pub struct Cli {
     #[arg(short, long, default_value_t = 16)]
     pub length: usize,
 
-    /// Number of passwords to generate
-    #[arg(short = 'n', long = "count", default_value_t = 1)]
-    pub count: usize,
-
-    /// Copy to clipboard
-    #[arg(short, long)]
-    pub copy: bool,
-
-    /// Show entropy and strength
-    #[arg(short, long)]
-    pub entropy: bool,
-
-    /// Quiet output (raw, for piping)
-    #[arg(short, long)]
-    pub quiet: bool,
+    #[command(flatten)]
+    pub emit: Emit,
 
     /// Exclude uppercase letters
     #[arg(short = 'U', long = "no-uppercase")]
@@ -49,6 +38,128 @@ pub struct Cli {
     /// Custom symbol set
     #[arg(long = "symbols")]
     pub custom_symbols: Option<String>,
+
+    /// Extra characters to allow (overrides -A)
+    #[arg(long = "include")]
+    pub include: Option<String>,
+
+    /// Characters to never use (overrides everything else)
+    #[arg(long = "exclude")]
+    pub exclude: Option<String>,
+
+    /// Add Unicode characters: latin1, greek, emoji or U+XXXX-U+YYYY (repeatable)
+    #[arg(long = "unicode", value_delimiter = ',')]
+    pub unicode: Vec<String>,
+
+    /// Only use characters typed the same way on all these layouts: qwerty, azerty, qwertz
+    #[arg(long = "keyboard-safe", value_name = "LAYOUTS", value_delimiter = ',')]
+    pub keyboard_safe: Vec<String>,
+
+    /// Forbid N identical characters in a row (e.g. 3 forbids "aaa")
+    #[arg(long = "no-repeat", value_name = "N")]
+    pub no_repeat: Option<usize>,
+
+    /// Forbid N ascending letters or digits in a row (e.g. 3 forbids "abc", "123")
+    #[arg(long = "no-sequential", value_name = "N")]
+    pub no_sequential: Option<usize>,
+
+    /// Use each character at most once
+    #[arg(long = "unique-chars")]
+    pub unique_chars: bool,
+
+    /// Minimum number of uppercase letters
+    #[arg(long = "min-uppercase", default_value_t = 0)]
+    pub min_uppercase: usize,
+
+    /// Minimum number of lowercase letters
+    #[arg(long = "min-lowercase", default_value_t = 0)]
+    pub min_lowercase: usize,
+
+    /// Minimum number of digits
+    #[arg(long = "min-digits", default_value_t = 0)]
+    pub min_digits: usize,
+
+    /// Minimum number of symbols
+    #[arg(long = "min-symbols", default_value_t = 0)]
+    pub min_symbols: usize,
+
+    /// Random source: thread (ChaCha12 keyed from the OS), os (getrandom) or file[:PATH] (/dev/urandom)
+    #[arg(
+        long = "rng",
+        value_name = "SOURCE",
+        default_value = "thread",
+        global = true
+    )]
+    pub rng: String,
+
+    /// INSECURE: derive output from a hex seed, for reproducible tests and fixtures
+    #[arg(
+        long = "seed",
+        value_name = "HEX",
+        global = true,
+        conflicts_with = "rng"
+    )]
+    pub seed: Option<String>,
+
+    /// Generate batches on N threads, each with its own CSPRNG (0: one per CPU)
+    #[arg(
+        short = 'j',
+        long = "threads",
+        value_name = "N",
+        default_value_t = 1,
+        global = true,
+        conflicts_with = "seed"
+    )]
+    pub threads: usize,
+
+    /// Write secrets to a file (mode 0600), or one file each into a directory, instead of the terminal
+    #[arg(short = 'o', long = "output", value_name = "PATH", global = true)]
+    pub output: Option<PathBuf>,
+
+    /// Let --output and --qr-output replace existing files
+    #[arg(long = "force", global = true)]
+    pub force: bool,
+
+    /// Output format: text, json, ndjson, csv, tsv or null (NUL-separated)
+    #[arg(
+        long = "format",
+        value_name = "FORMAT",
+        default_value = "text",
+        global = true
+    )]
+    pub format: String,
+
+    /// Print secrets as quoted literals: shell, json, yaml, toml, env, sql, xml or url
+    #[arg(long = "escape", value_name = "LANG", global = true)]
+    pub escape: Option<String>,
+
+    /// Never output the same secret twice in a batch
+    #[arg(long = "unique", global = true)]
+    pub unique: bool,
+
+    /// Only output passwords compliant with a policy: nist-800-63b, ad-complexity or a TOML file
+    #[arg(long = "policy", value_name = "FILE|PRESET", global = true)]
+    pub policy: Option<String>,
+}
+
+/// Output flags of every generating command.
+#[derive(Args, Clone, Copy)]
+pub struct Emit {
+    /// Number of values to generate
+    #[arg(short = 'n', long = "count", default_value_t = 1)]
+    pub count: usize,
+
+    /// Copy to clipboard
+    #[arg(short, long)]
+    pub copy: bool,
+
+    /// Show entropy and strength
+    #[arg(short, long)]
+    pub entropy: bool,
+
+    /// Quiet output (raw, for piping)
+    #[arg(short, long)]
+    pub quiet: bool,
 }
 
 #[derive(Subcommand)]
@@ -71,27 +182,223 @@ pub enum Command {
         #[arg(long)]
         append_digit: bool,
 
-        /// Number of passphrases to generate
-        #[arg(short = 'n', long = "count", default_value_t = 1)]
-        count: usize,
+        #[command(flatten)]
+        emit: Emit,
+    },
+    /// Generate a password from a mask (e.g. ?u?l?l?d-?d?d?d?d)
+    Mask {
+        /// Mask pattern: ?l ?u ?d ?s ?a ?h ?H ?1-?4, ?? for '?', other chars literal
+        pattern: String,
 
-        /// Copy to clipboard
-        #[arg(short, long)]
-        copy: bool,
+        /// Custom set for ?1
+        #[arg(short = '1', long = "custom-charset1")]
+        custom1: Option<String>,
 
-        /// Show entropy and strength
-        #[arg(short, long)]
-        entropy: bool,
+        /// Custom set for ?2
+        #[arg(short = '2', long = "custom-charset2")]
+        custom2: Option<String>,
+
+        /// Custom set for ?3
+        #[arg(short = '3', long = "custom-charset3")]
+        custom3: Option<String>,
+
+        /// Custom set for ?4
+        #[arg(short = '4', long = "custom-charset4")]
+        custom4: Option<String>,
+
+        #[command(flatten)]
+        emit: Emit,
+    },
+    /// Generate a pronounceable password (consonant/vowel syllables)
+    Pronounceable {
+        /// Password length
+        #[arg(short, long, default_value_t = 12)]
+        length: usize,
+
+        /// Number of digits to sprinkle in
+        #[arg(long, default_value_t = 0)]
+        digits: usize,
+
+        /// Number of symbols to sprinkle in
+        #[arg(long, default_value_t = 0)]
+        symbols: usize,
+
+        /// Capitalize the first letter
+        #[arg(long)]
+        capitalize: bool,
+
+        #[command(flatten)]
+        emit: Emit,
+    },
+    /// Generate random bytes encoded as text (API keys, HMAC keys, session secrets)
+    Token {
+        /// Number of random bytes
+        #[arg(short, long, default_value_t = 32)]
+        bytes: usize,
+
+        /// hex, HEX, base64, base64-nopad, base64url, base32, crockford, z85 or proquint
+        #[arg(short = 'E', long, default_value = "hex")]
+        encoding: String,
+
+        #[command(flatten)]
+        emit: Emit,
+    },
+    /// Generate an API key with a prefix and checksum (prefix_body+checksum)
+    Apikey {
+        #[command(subcommand)]
+        action: Option<ApikeyAction>,
+
+        /// Prefix, e.g. acme_live (letters, digits and _), followed by _
+        #[arg(short, long, default_value = "")]
+        prefix: String,
+
+        /// Number of random base62 characters
+        #[arg(short, long, default_value_t = 32)]
+        length: usize,
+
+        /// crc32, crc32c or none
+        #[arg(long, default_value = "crc32")]
+        checksum: String,
+
+        #[command(flatten)]
+        emit: Emit,
+    },
+    /// Generate an identifier (UUID, ULID, NanoID, KSUID) or inspect one
+    Id {
+        /// uuid4, uuid7, ulid, nanoid or ksuid, also accepted as --format <TYPE>
+        #[arg(
+            short = 'f',
+            long = "type",
+            value_name = "TYPE",
+            default_value = "uuid4"
+        )]
+        id_format: String,
+
+        /// UUIDs: uppercase hex digits
+        #[arg(long)]
+        uppercase: bool,
+
+        /// UUIDs: wrap in braces, {...}
+        #[arg(long, conflicts_with = "urn")]
+        braced: bool,
+
+        /// UUIDs: prefix with urn:uuid:
+        #[arg(long)]
+        urn: bool,
+
+        /// NanoID alphabet (default A-Za-z0-9_-)
+        #[arg(long)]
+        alphabet: Option<String>,
+
+        /// NanoID length (default 21)
+        #[arg(long)]
+        size: Option<usize>,
+
+        /// Embed this Unix time in milliseconds instead of now (uuid7, ulid, ksuid)
+        #[arg(long, value_name = "UNIX_MS")]
+        time: Option<u64>,
+
+        /// Print the format, timestamp and random portion of an existing ID
+        #[arg(long, value_name = "ID")]
+        inspect: Option<String>,
+
+        #[command(flatten)]
+        emit: Emit,
+    },
+    /// Generate a key pair for WireGuard, SSH (ed25519) or age
+    Keypair {
+        /// wireguard, ssh-ed25519 or age
+        #[arg(short = 't', long = "type")]
+        key_type: String,
+
+        /// Comment for SSH keys, e.g. user@host
+        #[arg(short = 'C', long, default_value = "")]
+        comment: String,
 
         /// Quiet output (raw, for piping)
         #[arg(short, long)]
         quiet: bool,
     },
+    /// Create TOTP/HOTP secrets and compute one-time codes
+    Otp {
+        #[command(subcommand)]
+        action: OtpAction,
+    },
     /// Use a predefined profile
     Profile {
         /// Profile name
         name: String,
 
+        /// Override the length of a character-set profile
+        #[arg(short, long)]
+        length: Option<usize>,
+
+        /// Override the symbol set of a character-set profile
+        #[arg(long = "symbols")]
+        custom_symbols: Option<String>,
+
+        /// Only use characters typed the same way on all these layouts: qwerty, azerty, qwertz
+        #[arg(long = "keyboard-safe", value_name = "LAYOUTS", value_delimiter = ',')]
+        keyboard_safe: Vec<String>,
+
+        /// Network name for the wifi profile's QR code
+        #[arg(long)]
+        ssid: Option<String>,
+
+        /// Print a Wi-Fi QR code to join the network (wifi profile)
+        #[arg(long, requires = "ssid", conflicts_with_all = ["count", "format"])]
+        qr: bool,
+
+        /// Write the Wi-Fi QR code to a new PNG or SVG file (wifi profile)
+        #[arg(long, value_name = "FILE", requires = "ssid", conflicts_with = "count")]
+        qr_output: Option<PathBuf>,
+
+        #[command(flatten)]
+        emit: Emit,
+    },
+    /// List available profiles
+    Profiles,
+    /// Derive a site password from a master secret (same inputs, same password)
+    Derive {
+        /// Site name, e.g. example.com
+        site: String,
+
+        /// Login or user name on the site
+        #[arg(short = 'u', long, default_value = "")]
+        login: String,
+
+        /// Counter, incremented to rotate the password
+        #[arg(long, default_value_t = 1)]
+        counter: u32,
+
+        /// Use a profile instead of the character options
+        #[arg(short, long)]
+        profile: Option<String>,
+
+        /// Password length
+        #[arg(short, long, default_value_t = 16)]
+        length: usize,
+
+        /// Exclude uppercase letters
+        #[arg(short = 'U', long = "no-uppercase")]
+        no_uppercase: bool,
+
+        /// Exclude lowercase letters
+        #[arg(short = 'L', long = "no-lowercase")]
+        no_lowercase: bool,
+
+        /// Exclude digits
+        #[arg(short = 'D', long = "no-digits")]
+        no_digits: bool,
+
+        /// Exclude symbols
+        #[arg(short = 'S', long = "no-symbols")]
+        no_symbols: bool,
+
+        /// Exclude ambiguous characters (0O, 1lI...)
+        #[arg(short = 'A', long = "no-ambiguous")]
+        no_ambiguous: bool,
+
         /// Copy to clipboard
         #[arg(short, long)]
         copy: bool,
@@ -103,11 +410,106 @@ pub enum Command {
         /// Quiet output (raw, for piping)
         #[arg(short, long)]
         quiet: bool,
+    },
+    /// Check a password against a policy (--policy)
+    Check {
+        /// Password to check (read from stdin if omitted)
+        password: Option<String>,
 
-        /// Number of passwords to generate
-        #[arg(short = 'n', long = "count", default_value_t = 1)]
-        count: usize,
+        /// Quiet output (exit code only)
+        #[arg(short, long)]
+        quiet: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ApikeyAction {
+    /// Check the checksum (and prefix) of a key offline
+    Verify {
+        /// Key to verify (read from stdin if omitted)
+        key: Option<String>,
+
+        /// Also require this prefix
+        #[arg(short, long)]
+        prefix: Option<String>,
+
+        /// crc32, crc32c or none
+        #[arg(long, default_value = "crc32")]
+        checksum: String,
+
+        /// Quiet output (exit code only)
+        #[arg(short, long)]
+        quiet: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum OtpAction {
+    /// Generate a base32 secret and its otpauth:// enrollment URI
+    New {
+        /// Service name shown in authenticator apps, e.g. Acme
+        #[arg(short, long, default_value = "")]
+        issuer: String,
+
+        /// Account name, e.g. alice@example.com
+        #[arg(short, long)]
+        account: String,
+
+        /// Code length (6 to 8)
+        #[arg(short, long, default_value_t = 6)]
+        digits: u32,
+
+        /// Seconds each TOTP code is valid
+        #[arg(long, default_value_t = 30)]
+        period: u64,
+
+        /// sha1, sha256 or sha512
+        #[arg(long, default_value = "sha1")]
+        algorithm: String,
+
+        /// Secret size in bytes (at least 10)
+        #[arg(short, long, default_value_t = 20)]
+        bytes: usize,
+
+        /// Counter-based HOTP instead of TOTP
+        #[arg(long)]
+        hotp: bool,
+
+        /// Initial HOTP counter
+        #[arg(long, default_value_t = 0, requires = "hotp")]
+        counter: u64,
+
+        /// Quiet output (secret and URI only)
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Compute the current code for a base32 secret or otpauth:// URI
+    Code {
+        /// Secret or URI (read from stdin if omitted)
+        secret: Option<String>,
+
+        /// Unix time to compute the TOTP code for, instead of now
+        #[arg(long, value_name = "UNIX")]
+        time: Option<u64>,
+
+        /// Compute the HOTP code for this counter
+        #[arg(long)]
+        counter: Option<u64>,
+
+        /// Code length (6 to 8), overriding the URI
+        #[arg(short, long)]
+        digits: Option<u32>,
+
+        /// Seconds each TOTP code is valid, overriding the URI
+        #[arg(long)]
+        period: Option<u64>,
+
+        /// sha1, sha256 or sha512, overriding the URI
+        #[arg(long)]
+        algorithm: Option<String>,
+
+        /// Quiet output (code only)
+        #[arg(short, long)]
+        quiet: bool,
     },
-    /// List available profiles
-    Profiles,
 }
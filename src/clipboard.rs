@@ -1,8 +1,13 @@
-use arboard::Clipboard;
-
+#[cfg(feature = "clipboard")]
 pub fn copy_to_clipboard(text: &str) -> Result<(), String> {
-    let mut clipboard = Clipboard::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
     clipboard
         .set_text(text)
         .map_err(|e| format!("Failed to copy to clipboard: {e}"))
 }
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_text: &str) -> Result<(), String> {
+    Err("Failed to copy to clipboard: pw was built without the clipboard feature".to_string())
+}
@@ -1,17 +1,104 @@
-use colored::Colorize;
-
+/// Strength estimate of a single password, from zxcvbn.
 pub struct EntropyInfo {
+    /// log2 of the estimated number of guesses.
     pub bits: f64,
+    /// zxcvbn score, from 0 (too guessable) to 4 (very unguessable).
     pub score: u8,
+    /// Time to crack against a slow hash at 10k guesses per second.
     pub crack_time: String,
 }
 
-#[allow(dead_code)]
+/// Entropy in bits of `length` independent uniform draws from
+/// `charset_size` characters.
 pub fn calculate_entropy(charset_size: usize, length: usize) -> f64 {
     (charset_size as f64).log2() * length as f64
 }
 
-#[allow(dead_code)]
+/// log2 of the binomial coefficient C(n, k).
+pub fn log2_binomial(n: usize, k: usize) -> f64 {
+    (0..k)
+        .map(|i| ((n - i) as f64 / (k - i) as f64).log2())
+        .sum()
+}
+
+/// Entropy of a uniform choice among strings of `length` drawn from disjoint
+/// classes of the given sizes, where class `i` appears at least `minimums[i]`
+/// times.
+///
+/// Counts the valid strings class by class: with `f(r)` the number of valid
+/// strings of length `r` over the classes seen so far, adding a class of size
+/// `n` and minimum `m` gives `sum over k >= m of C(r, k) * n^k * f(r - k)`.
+/// Everything is kept in log2 space so long passwords do not overflow.
+pub fn calculate_constrained_entropy(sizes: &[usize], minimums: &[usize], length: usize) -> f64 {
+    class_count_entropy(sizes, minimums, length, |n, k| k as f64 * (n as f64).log2())
+}
+
+/// Same as [`calculate_constrained_entropy`], but no character may appear
+/// twice: `n^k` becomes the falling factorial `n! / (n - k)!`.
+pub fn calculate_distinct_constrained_entropy(
+    sizes: &[usize],
+    minimums: &[usize],
+    length: usize,
+) -> f64 {
+    class_count_entropy(sizes, minimums, length, |n, k| {
+        if k > n {
+            f64::NEG_INFINITY
+        } else {
+            (0..k).map(|i| ((n - i) as f64).log2()).sum()
+        }
+    })
+}
+
+/// Shared class-by-class count, where `log_weight(n, k)` is log2 of the number
+/// of ways to fill `k` given positions from a class of size `n`.
+fn class_count_entropy(
+    sizes: &[usize],
+    minimums: &[usize],
+    length: usize,
+    log_weight: impl Fn(usize, usize) -> f64,
+) -> f64 {
+    let log_factorial: Vec<f64> = std::iter::once(0.0)
+        .chain((1..=length).scan(0.0, |acc, i| {
+            *acc += (i as f64).log2();
+            Some(*acc)
+        }))
+        .collect();
+    let log_binomial =
+        |r: usize, k: usize| log_factorial[r] - log_factorial[k] - log_factorial[r - k];
+
+    let mut counts = vec![f64::NEG_INFINITY; length + 1];
+    counts[0] = 0.0;
+
+    for (&size, &min) in sizes.iter().zip(minimums) {
+        let next: Vec<f64> = (0..=length)
+            .map(|r| {
+                let terms: Vec<f64> = (min..=r)
+                    .map(|k| {
+                        let weight = if k == 0 { 0.0 } else { log_weight(size, k) };
+                        log_binomial(r, k) + weight + counts[r - k]
+                    })
+                    .filter(|t| t.is_finite())
+                    .collect();
+                log2_sum(&terms)
+            })
+            .collect();
+        counts = next;
+    }
+
+    counts[length]
+}
+
+/// log2 of a sum of terms given in log2 space.
+pub fn log2_sum(terms: &[f64]) -> f64 {
+    let max = terms.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + terms.iter().map(|t| (t - max).exp2()).sum::<f64>().log2()
+}
+
+/// Entropy in bits of a diceware passphrase, with an optional appended
+/// digit.
 pub fn calculate_passphrase_entropy(
     wordlist_size: usize,
     word_count: usize,
@@ -24,6 +111,7 @@ pub fn calculate_passphrase_entropy(
     bits
 }
 
+/// Estimates how guessable an existing password is with zxcvbn.
 pub fn analyze_password(password: &str) -> EntropyInfo {
     let estimate = zxcvbn::zxcvbn(password, &[]);
     let score = estimate.score().into();
@@ -42,39 +130,15 @@ fn format_crack_time(estimate: &zxcvbn::Entropy) -> String {
     format!("{seconds}")
 }
 
-pub fn format_entropy_display(info: &EntropyInfo) -> String {
-    let bar = strength_bar(info.score);
-    let label = strength_label(info.score);
-    format!(
-        "  Entropy: {:.1} bits | Strength: {} {} | Crack time: {}",
-        info.bits, bar, label, info.crack_time
-    )
-}
-
-fn strength_bar(score: u8) -> String {
-    let filled = score as usize + 1;
-    let empty = 4_usize.saturating_sub(score as usize);
-    let bar_filled: String = (0..filled).map(|_| '\u{2588}').collect(); // Full block
-    let bar_empty: String = (0..empty).map(|_| '\u{2591}').collect(); // Light shade
-
+/// The zxcvbn score (0 to 4) in words.
+pub fn strength_label(score: u8) -> &'static str {
     match score {
-        0 => format!("{}{}", bar_filled.red(), bar_empty.dimmed()),
-        1 => format!("{}{}", bar_filled.red(), bar_empty.dimmed()),
-        2 => format!("{}{}", bar_filled.yellow(), bar_empty.dimmed()),
-        3 => format!("{}{}", bar_filled.green(), bar_empty.dimmed()),
-        4 => format!("{}{}", bar_filled.bright_green(), bar_empty.dimmed()),
-        _ => format!("{}{}", bar_filled, bar_empty),
-    }
-}
-
-fn strength_label(score: u8) -> String {
-    match score {
-        0 => "Very weak".red().to_string(),
-        1 => "Weak".red().to_string(),
-        2 => "Fair".yellow().to_string(),
-        3 => "Strong".green().to_string(),
-        4 => "Very strong".bright_green().to_string(),
-        _ => "Unknown".to_string(),
+        0 => "Very weak",
+        1 => "Weak",
+        2 => "Fair",
+        3 => "Strong",
+        4 => "Very strong",
+        _ => "Unknown",
     }
 }
 
@@ -89,6 +153,52 @@ mod tests {
         assert!((bits - 105.0).abs() < 1.0);
     }
 
+    #[test]
+    fn constrained_entropy_without_minimums_matches_unconstrained() {
+        let bits = calculate_constrained_entropy(&[26, 26, 10, 29], &[0, 0, 0, 0], 16);
+        assert!((bits - calculate_entropy(91, 16)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn constrained_entropy_counts_exactly() {
+        // Length 2 over {a, b} and {1}, at least one digit: a1, b1, 1a, 1b, 11
+        let bits = calculate_constrained_entropy(&[2, 1], &[0, 1], 2);
+        assert!((bits - 5f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn constrained_entropy_with_empty_class() {
+        let bits = calculate_constrained_entropy(&[26, 0], &[1, 0], 4);
+        assert!((bits - calculate_entropy(26, 4)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn distinct_entropy_counts_exactly() {
+        // 3 distinct out of 5 letters with no minimum: 5 * 4 * 3
+        let bits = calculate_distinct_constrained_entropy(&[5], &[0], 3);
+        assert!((bits - 60f64.log2()).abs() < 1e-9);
+        // Length 2 over {a, b} and {1, 2}, distinct, at least one digit:
+        // 4 * 3 orderings minus the 2 all-letter ones
+        let bits = calculate_distinct_constrained_entropy(&[2, 2], &[0, 1], 2);
+        assert!((bits - 10f64.log2()).abs() < 1e-9);
+        assert_eq!(
+            calculate_distinct_constrained_entropy(&[3], &[0], 4),
+            f64::NEG_INFINITY
+        );
+    }
+
+    #[test]
+    fn constrained_entropy_impossible_is_negative_infinity() {
+        let bits = calculate_constrained_entropy(&[10, 10], &[2, 2], 3);
+        assert_eq!(bits, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn binomial() {
+        assert!((log2_binomial(5, 2) - 10f64.log2()).abs() < 1e-9);
+        assert_eq!(log2_binomial(7, 0), 0.0);
+    }
+
     #[test]
     fn passphrase_entropy_calculation() {
         // 7776 words, 4 words = ~51.7 bits
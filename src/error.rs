@@ -0,0 +1,219 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// No character set is left to draw from.
+    EmptyCharset,
+    /// `--symbols` was given an empty string.
+    EmptySymbols,
+    /// Password length of zero.
+    InvalidLength,
+    /// Batch count of zero, which streams without end, with `--copy`,
+    /// `--unique` or `--output`.
+    InvalidCount,
+    /// Passphrase with zero words.
+    InvalidWordCount,
+    /// A minimum was set for a character class that is excluded.
+    MinimumExcluded {
+        class: &'static str,
+        min: usize,
+    },
+    /// The minimums (or required digits and symbols) add up to more than
+    /// the password length.
+    MinimumsExceedLength {
+        required: usize,
+        length: usize,
+    },
+    /// The minimums and constraints leave too few valid passwords to sample
+    /// efficiently.
+    ConstraintsTooRestrictive {
+        length: usize,
+    },
+    /// A mask pattern or custom set could not be parsed.
+    InvalidMask(String),
+    /// A Unicode class name or `U+XXXX-U+YYYY` range could not be parsed.
+    InvalidUnicodeClass(String),
+    /// A Unicode class has no characters usable in a password.
+    EmptyUnicodeClass(String),
+    /// A custom character is not a single standalone code point.
+    UnsupportedCharacter(String),
+    /// A repeat, sequence or uniqueness constraint is invalid.
+    InvalidConstraint(String),
+    /// No compliant password was produced, or a checked password fails the
+    /// policy. Holds the failing rules.
+    PolicyViolation(Vec<String>),
+    /// A policy file could not be read or parsed.
+    InvalidPolicy(String),
+    /// A `--keyboard-safe` layout name is not known.
+    UnknownLayout(String),
+    /// A `--seed` is not valid hex or is too long.
+    InvalidSeed(String),
+    /// `--seed` was combined with `--copy`.
+    SeedWithCopy,
+    /// The random source failed to provide bytes.
+    Entropy(String),
+    /// A `--rng` backend name is not known.
+    UnknownRng(String),
+    /// The master secret or site for `pw derive` is unusable.
+    InvalidSecret(String),
+    /// A token encoding is unknown or cannot encode the byte count.
+    InvalidToken(String),
+    /// An ID format or option is unknown, or an ID cannot be inspected.
+    InvalidId(String),
+    /// An API key option is invalid, or a key fails verification.
+    InvalidApiKey(String),
+    /// A key type is unknown, or a private key file cannot be written.
+    InvalidKeyPair(String),
+    /// An OTP secret, URI or parameter is invalid.
+    InvalidOtp(String),
+    /// A Wi-Fi SSID is unusable, or wifi keys would not be valid WPA keys.
+    InvalidWifi(String),
+    /// A QR code cannot be encoded or written.
+    QrCode(String),
+    /// Generated secrets cannot be written out.
+    Output(String),
+    /// A unique batch is too large for its output space, or keeps drawing
+    /// duplicates.
+    NotUnique(String),
+    /// A `--format` name is not known.
+    UnknownFormat(String),
+    /// An `--escape` name is not known.
+    UnknownEscape(String),
+    /// A profile given to `pw derive` embeds the current time, so its output
+    /// cannot be reproduced.
+    NotDeterministic(String),
+    UnknownProfile(String),
+}
+
+impl Error {
+    /// Process exit code for this error, as documented in the user manual.
+    /// Code 2 is left to clap for command-line usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::UnknownProfile(_) => 1,
+            Error::EmptyCharset => 3,
+            Error::EmptySymbols => 4,
+            Error::InvalidLength => 5,
+            Error::InvalidCount => 6,
+            Error::InvalidWordCount => 7,
+            Error::MinimumExcluded { .. } => 8,
+            Error::MinimumsExceedLength { .. } => 9,
+            Error::ConstraintsTooRestrictive { .. } => 10,
+            Error::InvalidMask(_) => 11,
+            Error::InvalidUnicodeClass(_) => 12,
+            Error::EmptyUnicodeClass(_) => 13,
+            Error::UnsupportedCharacter(_) => 14,
+            Error::InvalidConstraint(_) => 15,
+            Error::PolicyViolation(_) => 16,
+            Error::InvalidPolicy(_) => 17,
+            Error::UnknownLayout(_) => 18,
+            Error::InvalidSeed(_) => 19,
+            Error::SeedWithCopy => 20,
+            Error::Entropy(_) => 21,
+            Error::UnknownRng(_) => 22,
+            Error::InvalidSecret(_) => 23,
+            Error::InvalidToken(_) => 24,
+            Error::InvalidId(_) => 25,
+            Error::InvalidApiKey(_) => 26,
+            Error::InvalidKeyPair(_) => 27,
+            Error::InvalidOtp(_) => 28,
+            Error::InvalidWifi(_) => 29,
+            Error::QrCode(_) => 30,
+            Error::Output(_) => 31,
+            Error::NotUnique(_) => 32,
+            Error::UnknownFormat(_) => 33,
+            Error::UnknownEscape(_) => 34,
+            Error::NotDeterministic(_) => 35,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EmptyCharset => write!(f, "Character set is empty: enable at least one class"),
+            Error::EmptySymbols => write!(f, "Custom symbol set is empty"),
+            Error::InvalidLength => write!(f, "Length must be at least 1"),
+            Error::InvalidCount => write!(
+                f,
+                "Count must be at least 1 with --copy, --unique or --output, -n 0 streams without end"
+            ),
+            Error::InvalidWordCount => write!(f, "Word count must be at least 1"),
+            Error::MinimumExcluded { class, min } => {
+                write!(
+                    f,
+                    "At least {min} {class} required, but {class} are excluded"
+                )
+            }
+            Error::MinimumsExceedLength { required, length } => write!(
+                f,
+                "Minimum character counts add up to {required}, more than the length {length}"
+            ),
+            Error::ConstraintsTooRestrictive { length } => write!(
+                f,
+                "Character requirements are too restrictive for length {length}"
+            ),
+            Error::InvalidMask(reason) => write!(f, "Invalid mask: {reason}"),
+            Error::InvalidUnicodeClass(class) => write!(
+                f,
+                "Invalid Unicode class: {class} (expected latin1, greek, emoji or U+XXXX-U+YYYY)"
+            ),
+            Error::EmptyUnicodeClass(class) => write!(
+                f,
+                "Unicode class {class} has no standalone printable characters"
+            ),
+            Error::UnsupportedCharacter(g) => write!(
+                f,
+                "Unsupported character {g:?}: custom characters must be single standalone code points"
+            ),
+            Error::InvalidConstraint(reason) => write!(f, "Invalid constraint: {reason}"),
+            Error::PolicyViolation(rules) => write!(
+                f,
+                "Password does not comply with the policy: {}",
+                rules.join("; ")
+            ),
+            Error::InvalidPolicy(reason) => write!(f, "Invalid policy: {reason}"),
+            Error::UnknownLayout(name) => write!(
+                f,
+                "Unknown keyboard layout: {name} (expected qwerty, azerty or qwertz)"
+            ),
+            Error::InvalidSeed(reason) => write!(f, "Invalid seed: {reason}"),
+            Error::SeedWithCopy => write!(
+                f,
+                "--seed output is predictable and cannot be copied to the clipboard"
+            ),
+            Error::Entropy(reason) => write!(f, "Cannot obtain random bytes: {reason}"),
+            Error::UnknownRng(name) => write!(
+                f,
+                "Unknown random source: {name} (expected os, thread, file or file:<path>)"
+            ),
+            Error::InvalidSecret(reason) => write!(f, "Invalid master secret: {reason}"),
+            Error::InvalidToken(reason) => write!(f, "Invalid token: {reason}"),
+            Error::InvalidId(reason) => write!(f, "Invalid ID: {reason}"),
+            Error::InvalidApiKey(reason) => write!(f, "Invalid API key: {reason}"),
+            Error::InvalidKeyPair(reason) => write!(f, "Cannot create key pair: {reason}"),
+            Error::InvalidOtp(reason) => write!(f, "Invalid OTP: {reason}"),
+            Error::InvalidWifi(reason) => write!(f, "Invalid Wi-Fi network: {reason}"),
+            Error::QrCode(reason) => write!(f, "Cannot create QR code: {reason}"),
+            Error::Output(reason) => write!(f, "Cannot write output: {reason}"),
+            Error::NotUnique(reason) => write!(f, "Cannot guarantee distinct secrets: {reason}"),
+            Error::UnknownFormat(name) => write!(
+                f,
+                "Unknown output format: {name} (expected text, json, ndjson, csv, tsv or null)"
+            ),
+            Error::UnknownEscape(name) => write!(
+                f,
+                "Unknown escape: {name} (expected shell, json, yaml, toml, env, sql, xml or url)"
+            ),
+            Error::NotDeterministic(name) => write!(
+                f,
+                "Cannot derive from the {name} profile: it embeds the current time"
+            ),
+            Error::UnknownProfile(name) => write!(f, "Unknown profile: {name}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
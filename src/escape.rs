@@ -0,0 +1,301 @@
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::format::json_string;
+
+/// A language to write a secret as a literal of with `--escape`, so that it
+/// survives being pasted into a script or configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    /// A POSIX shell word in single quotes.
+    Shell,
+    /// A JSON string.
+    Json,
+    /// A YAML flow scalar: single-quoted, or double-quoted when the secret
+    /// has control characters.
+    Yaml,
+    /// A TOML literal string, or a basic string when the secret has a single
+    /// quote or control characters.
+    Toml,
+    /// A `.env` value: single-quoted, or double-quoted with `\`, `"`, `$` and
+    /// backticks escaped when the secret has a single quote. Control
+    /// characters are kept as they are, which `sh` and dotenv both read back.
+    Env,
+    /// A standard SQL string literal, with single quotes doubled.
+    Sql,
+    /// XML character data, also safe in attribute values.
+    Xml,
+    /// Percent-encoding of everything but unreserved characters (RFC 3986).
+    Url,
+}
+
+impl Escape {
+    pub const ALL: [Escape; 8] = [
+        Escape::Shell,
+        Escape::Json,
+        Escape::Yaml,
+        Escape::Toml,
+        Escape::Env,
+        Escape::Sql,
+        Escape::Xml,
+        Escape::Url,
+    ];
+
+    /// `secret` as a literal of this language.
+    pub fn escape(self, secret: &str) -> String {
+        let mut out = String::with_capacity(secret.len() + 2);
+        let control = secret.contains(char::is_control);
+        match self {
+            Escape::Shell => {
+                out.push('\'');
+                out.push_str(&secret.replace('\'', r"'\''"));
+                out.push('\'');
+            }
+            Escape::Json => json_string(&mut out, secret),
+            // JSON strings are YAML double-quoted scalars, and TOML basic
+            // strings take the same escapes
+            Escape::Yaml if control => json_string(&mut out, secret),
+            Escape::Toml if control || secret.contains('\'') => json_string(&mut out, secret),
+            Escape::Yaml => {
+                out.push('\'');
+                out.push_str(&secret.replace('\'', "''"));
+                out.push('\'');
+            }
+            Escape::Toml => {
+                out.push('\'');
+                out.push_str(secret);
+                out.push('\'');
+            }
+            Escape::Env if secret.contains('\'') => {
+                out.push('"');
+                for c in secret.chars() {
+                    if matches!(c, '\\' | '"' | '$' | '`') {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+                out.push('"');
+            }
+            Escape::Env => {
+                out.push('\'');
+                out.push_str(secret);
+                out.push('\'');
+            }
+            Escape::Sql => {
+                out.push('\'');
+                out.push_str(&secret.replace('\'', "''"));
+                out.push('\'');
+            }
+            Escape::Xml => {
+                for c in secret.chars() {
+                    match c {
+                        '&' => out.push_str("&amp;"),
+                        '<' => out.push_str("&lt;"),
+                        '>' => out.push_str("&gt;"),
+                        '"' => out.push_str("&quot;"),
+                        '\'' => out.push_str("&apos;"),
+                        // Attribute values would turn them into spaces
+                        c if c.is_control() => {
+                            let _ = write!(out, "&#{};", c as u32);
+                        }
+                        c => out.push(c),
+                    }
+                }
+            }
+            Escape::Url => out.push_str(&percent_encode(secret)),
+        }
+        out
+    }
+}
+
+impl fmt::Display for Escape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Escape::Shell => "shell",
+            Escape::Json => "json",
+            Escape::Yaml => "yaml",
+            Escape::Toml => "toml",
+            Escape::Env => "env",
+            Escape::Sql => "sql",
+            Escape::Xml => "xml",
+            Escape::Url => "url",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Escape {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Escape::ALL
+            .into_iter()
+            .find(|escape| escape.to_string() == s)
+            .ok_or_else(|| Error::UnknownEscape(s.to_string()))
+    }
+}
+
+/// Percent-encodes every byte of `s` but unreserved characters (RFC 3986).
+pub fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every symbol of the default set, quotes, control characters and
+    /// non-ASCII text.
+    const SECRETS: [&str; 8] = [
+        "",
+        "plain",
+        "!@#$%^&*()-_=+[]{};:,.<>?/~`",
+        r#"it's "quoted" \ $HOME `id` # not a comment"#,
+        "line\nbreak\ttab\r\u{1}\u{7f} it's",
+        "c1\u{85}\u{9b}",
+        "  spaces  ",
+        "ünïcødé 密码 🔑",
+    ];
+
+    fn round_trip(escape: Escape, decode: impl Fn(&str) -> String) {
+        for secret in SECRETS {
+            let escaped = escape.escape(secret);
+            assert_eq!(decode(&escaped), secret, "{escape}: {escaped}");
+        }
+    }
+
+    /// A document with a single `secret` key, as the YAML and TOML tests
+    /// parse it.
+    #[derive(serde::Deserialize)]
+    struct Value {
+        secret: String,
+    }
+
+    fn unquote(s: &str, quote: char) -> &str {
+        s.strip_prefix(quote).unwrap().strip_suffix(quote).unwrap()
+    }
+
+    /// What `sh` makes of the word `escaped`.
+    #[cfg(unix)]
+    fn sh_printf(escaped: &str) -> String {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {escaped}"))
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_round_trip() {
+        round_trip(Escape::Shell, sh_printf);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip(Escape::Json, |escaped| {
+            serde_json::from_str::<String>(escaped).unwrap()
+        });
+        for escape in [Escape::Json, Escape::Yaml, Escape::Toml] {
+            let escaped = escape.escape("c1\u{85}\u{9b}");
+            assert!(!escaped.contains(char::is_control), "{escape}: {escaped}");
+        }
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        round_trip(Escape::Yaml, |escaped| {
+            serde_yaml_ng::from_str::<Value>(&format!("secret: {escaped}"))
+                .unwrap()
+                .secret
+        });
+        assert_eq!(Escape::Yaml.escape("it's #1"), "'it''s #1'");
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        round_trip(Escape::Toml, |escaped| {
+            toml::from_str::<Value>(&format!("secret = {escaped}"))
+                .unwrap()
+                .secret
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn env_round_trip() {
+        round_trip(Escape::Env, sh_printf);
+        assert_eq!(Escape::Env.escape("a'$b"), r#""a'\$b""#);
+        assert_eq!(Escape::Env.escape("a'\nb"), "\"a'\nb\"");
+    }
+
+    #[test]
+    fn sql_round_trip() {
+        round_trip(Escape::Sql, |escaped| {
+            unquote(escaped, '\'').replace("''", "'")
+        });
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        round_trip(Escape::Xml, |escaped| {
+            let mut out = String::new();
+            let mut rest = escaped;
+            while let Some(start) = rest.find('&') {
+                out.push_str(&rest[..start]);
+                let end = start + rest[start..].find(';').unwrap();
+                out.push(match &rest[start + 1..end] {
+                    "amp" => '&',
+                    "lt" => '<',
+                    "gt" => '>',
+                    "quot" => '"',
+                    "apos" => '\'',
+                    code => char::from_u32(code[1..].parse().unwrap()).unwrap(),
+                });
+                rest = &rest[end + 1..];
+            }
+            out.push_str(rest);
+            out
+        });
+    }
+
+    #[test]
+    fn url_round_trip() {
+        round_trip(Escape::Url, |escaped| {
+            let mut bytes = Vec::new();
+            let mut rest = escaped.as_bytes();
+            while let Some((&b, tail)) = rest.split_first() {
+                if b == b'%' {
+                    let hex = std::str::from_utf8(&tail[..2]).unwrap();
+                    bytes.push(u8::from_str_radix(hex, 16).unwrap());
+                    rest = &tail[2..];
+                } else {
+                    bytes.push(b);
+                    rest = tail;
+                }
+            }
+            String::from_utf8(bytes).unwrap()
+        });
+        assert_eq!(Escape::Url.escape("a b/é"), "a%20b%2F%C3%A9");
+    }
+
+    #[test]
+    fn escape_names() {
+        for escape in Escape::ALL {
+            assert_eq!(escape.to_string().parse::<Escape>(), Ok(escape));
+        }
+        assert_eq!(
+            "csv".parse::<Escape>(),
+            Err(Error::UnknownEscape("csv".to_string()))
+        );
+    }
+}
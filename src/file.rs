@@ -0,0 +1,151 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// A new file only its owner can read (mode 0600), written under a
+/// temporary name next to `path` and moved into place by [`commit`], so that
+/// nobody ever reads part of a secret. Dropping it uncommitted removes the
+/// temporary file.
+///
+/// [`commit`]: SecretFile::commit
+pub struct SecretFile {
+    path: PathBuf,
+    temp: PathBuf,
+    file: File,
+    overwrite: bool,
+    committed: bool,
+}
+
+impl SecretFile {
+    /// Starts writing `path`. Unless `overwrite` is set, an existing file is
+    /// an error, both now and when committing.
+    pub fn create(path: &Path, overwrite: bool) -> io::Result<Self> {
+        if !overwrite && fs::symlink_metadata(path).is_ok() {
+            return Err(exists());
+        }
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "not a file name"))?;
+        let mut suffix = [0u8; 8];
+        getrandom::fill(&mut suffix).map_err(io::Error::other)?;
+        let suffix: String = suffix.iter().map(|b| format!("{b:02x}")).collect();
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{suffix}.tmp"));
+        let temp = path.with_file_name(temp_name);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(&temp)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp,
+            file,
+            overwrite,
+            committed: false,
+        })
+    }
+
+    /// Flushes the contents to disk and gives the file its final name.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.file.sync_all()?;
+        if self.overwrite {
+            fs::rename(&self.temp, &self.path)?;
+        } else {
+            // Unlike a rename, a link fails if the target appeared meanwhile
+            fs::hard_link(&self.temp, &self.path).map_err(|e| match e.kind() {
+                ErrorKind::AlreadyExists => exists(),
+                _ => e,
+            })?;
+            fs::remove_file(&self.temp)?;
+        }
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for SecretFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for SecretFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Writes `contents` to `path` as a [`SecretFile`].
+pub fn write_secret(path: &Path, contents: &[u8], overwrite: bool) -> io::Result<()> {
+    let mut file = SecretFile::create(path, overwrite)?;
+    file.write_all(contents)?;
+    file.commit()
+}
+
+fn exists() -> io::Error {
+    io::Error::new(
+        ErrorKind::AlreadyExists,
+        "already exists, use --force to overwrite",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        write_secret(&path, b"secret\n", false).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret\n");
+        assert!(write_secret(&path, b"other\n", false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwrite_replaces_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_secret(&path, b"new\n", true).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+    }
+
+    #[test]
+    fn uncommitted_file_leaves_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        let mut file = SecretFile::create(&path, false).unwrap();
+        file.write_all(b"partial").unwrap();
+        drop(file);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let file = SecretFile::create(&path, false).unwrap();
+        fs::write(&path, "raced").unwrap();
+        assert_eq!(file.commit().unwrap_err().kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "raced");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,396 @@
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+
+use zeroize::Zeroize;
+
+use crate::error::{Error, Result};
+
+/// How `--format` lays out records: the human-readable text of each
+/// command, or one of the machine-readable formats below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    /// A JSON array of objects.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// RFC 4180 CSV with a header row.
+    Csv,
+    /// Tab-separated values with a header row; tabs, newlines and
+    /// backslashes in values are escaped as `\t`, `\n` and `\\`.
+    Tsv,
+    /// The first field of each record followed by a NUL byte, for
+    /// `xargs -0`.
+    Null,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::Text,
+        Format::Json,
+        Format::Ndjson,
+        Format::Csv,
+        Format::Tsv,
+        Format::Null,
+    ];
+
+    /// Written once before the records of `keys`.
+    pub fn header(self, keys: &[&str]) -> String {
+        match self {
+            Format::Json => "[\n".to_string(),
+            Format::Csv => {
+                let keys: Vec<String> = keys.iter().map(|k| csv_field(k)).collect();
+                format!("{}\n", keys.join(","))
+            }
+            Format::Tsv => {
+                let keys: Vec<String> = keys.iter().map(|k| tsv_field(k)).collect();
+                format!("{}\n", keys.join("\t"))
+            }
+            Format::Text | Format::Ndjson | Format::Null => String::new(),
+        }
+    }
+
+    /// Written between two records.
+    pub fn separator(self) -> &'static str {
+        match self {
+            Format::Json => ",\n",
+            _ => "",
+        }
+    }
+
+    /// Written once after the last record.
+    pub fn footer(self) -> &'static str {
+        match self {
+            Format::Json => "\n]\n",
+            _ => "",
+        }
+    }
+
+    /// One record, with its line ending except in JSON arrays. Text records
+    /// are written as their first field on a line.
+    pub fn record(self, record: &Record) -> String {
+        let mut out = String::new();
+        match self {
+            Format::Json => {
+                out.push_str("  ");
+                json_object(&mut out, record);
+            }
+            Format::Ndjson => {
+                json_object(&mut out, record);
+                out.push('\n');
+            }
+            Format::Csv | Format::Tsv => {
+                let (field, separator): (fn(&str) -> String, _) = match self {
+                    Format::Csv => (csv_field, ","),
+                    _ => (tsv_field, "\t"),
+                };
+                for (i, (_, value)) in record.fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(separator);
+                    }
+                    let mut text = value.to_text();
+                    out.push_str(&field(&text));
+                    text.zeroize();
+                }
+                out.push('\n');
+            }
+            Format::Text | Format::Null => {
+                if let Some((_, value)) = record.fields.first() {
+                    let mut text = value.to_text();
+                    out.push_str(&text);
+                    text.zeroize();
+                }
+                out.push(if self == Format::Null { '\0' } else { '\n' });
+            }
+        }
+        out
+    }
+
+    /// Header, records and footer together.
+    pub fn records(self, records: &[Record]) -> String {
+        let keys = records.first().map(Record::keys).unwrap_or_default();
+        let mut out = self.header(&keys);
+        for (i, record) in records.iter().enumerate() {
+            if i > 0 {
+                out.push_str(self.separator());
+            }
+            out.push_str(&self.record(record));
+        }
+        out.push_str(self.footer());
+        out
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Null => "null",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.to_string() == s)
+            .ok_or_else(|| Error::UnknownFormat(s.to_string()))
+    }
+}
+
+/// A value of a record field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(u64),
+    /// Written with at most two decimals.
+    Float(f64),
+    Str(String),
+    /// Named strings, such as generator parameters: a JSON object, or
+    /// `name=value` pairs joined by `;` elsewhere.
+    Map(Vec<(&'static str, String)>),
+}
+
+impl Value {
+    fn to_text(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::Bool(b) => b.to_string(),
+            Value::Int(n) => n.to_string(),
+            Value::Float(x) => float(*x),
+            Value::Str(s) => s.clone(),
+            Value::Map(pairs) => pairs
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(";"),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        Value::Int(n)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Self {
+        Value::Int(n as u64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Float(x)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// Named fields in a fixed order. The first field is the main value, the
+/// only one written by the `null` format. String values are zeroed when the
+/// record is dropped, as they usually hold a secret.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl Record {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, key: &'static str, value: impl Into<Value>) -> Self {
+        self.fields.push((key, value.into()));
+        self
+    }
+
+    pub fn keys(&self) -> Vec<&'static str> {
+        self.fields.iter().map(|(key, _)| *key).collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+}
+
+impl Drop for Record {
+    fn drop(&mut self) {
+        for (_, value) in &mut self.fields {
+            if let Value::Str(s) = value {
+                s.zeroize();
+            }
+        }
+    }
+}
+
+fn float(x: f64) -> String {
+    let rounded = (x * 100.0).round() / 100.0;
+    format!("{rounded}")
+}
+
+fn json_object(out: &mut String, record: &Record) {
+    out.push('{');
+    for (i, (key, value)) in record.fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json_string(out, key);
+        out.push(':');
+        match value {
+            Value::Null => out.push_str("null"),
+            Value::Float(x) if !x.is_finite() => out.push_str("null"),
+            Value::Bool(_) | Value::Int(_) | Value::Float(_) => out.push_str(&value.to_text()),
+            Value::Str(s) => json_string(out, s),
+            Value::Map(pairs) => {
+                out.push('{');
+                for (i, (name, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    json_string(out, name);
+                    out.push(':');
+                    json_string(out, value);
+                }
+                out.push('}');
+            }
+        }
+    }
+    out.push('}');
+}
+
+/// Appends `s` as a JSON string literal (RFC 8259).
+pub fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) || s.starts_with(' ') || s.ends_with(' ') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn tsv_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> Record {
+        Record::new()
+            .with("secret", "a\"b,c\td\\")
+            .with("length", 8usize)
+            .with("entropy", Some(52.4567))
+            .with("alphabet", None::<usize>)
+            .with("valid", true)
+            .with("parameters", Value::Map(vec![("length", "8".to_string())]))
+    }
+
+    #[test]
+    fn json_records() {
+        assert_eq!(
+            Format::Ndjson.record(&record()),
+            "{\"secret\":\"a\\\"b,c\\td\\\\\",\"length\":8,\"entropy\":52.46,\
+             \"alphabet\":null,\"valid\":true,\"parameters\":{\"length\":\"8\"}}\n"
+        );
+        let two = [Record::new().with("n", 1u64), Record::new().with("n", 2u64)];
+        assert_eq!(
+            Format::Json.records(&two),
+            "[\n  {\"n\":1},\n  {\"n\":2}\n]\n"
+        );
+        let mut out = String::new();
+        json_string(&mut out, "\u{1}\u{7f}\u{85}é");
+        assert_eq!(out, "\"\\u0001\\u007f\\u0085é\"");
+    }
+
+    #[test]
+    fn csv_and_tsv_records() {
+        assert_eq!(
+            Format::Csv.records(&[record()]),
+            "secret,length,entropy,alphabet,valid,parameters\n\
+             \"a\"\"b,c\td\\\",8,52.46,,true,length=8\n"
+        );
+        assert_eq!(
+            Format::Tsv.record(&record()),
+            "a\"b,c\\td\\\\\t8\t52.46\t\ttrue\tlength=8\n"
+        );
+    }
+
+    #[test]
+    fn null_writes_first_field() {
+        let two = [record(), Record::new().with("secret", "x")];
+        assert_eq!(Format::Null.records(&two), "a\"b,c\td\\\0x\0");
+    }
+
+    #[test]
+    fn format_names() {
+        for format in Format::ALL {
+            assert_eq!(format.to_string().parse::<Format>(), Ok(format));
+        }
+        assert_eq!(
+            "xml".parse::<Format>(),
+            Err(Error::UnknownFormat("xml".to_string()))
+        );
+    }
+}
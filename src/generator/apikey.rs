@@ -0,0 +1,305 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::Generator;
+use super::charset::CharsetBuilder;
+use super::random;
+use super::token::BASE62;
+use crate::entropy;
+use crate::error::{Error, Result};
+use crate::rng::RandomSource;
+
+/// Base62 digits needed for any 32-bit value (62^6 > 2^32).
+const CRC_DIGITS: usize = 6;
+/// Reflected CRC-32 polynomials.
+const CRC32_IEEE: u32 = 0xedb8_8320;
+const CRC32_CASTAGNOLI: u32 = 0x82f6_3b78;
+
+/// The checksum appended to an API key, so that typos and truncated keys
+/// are caught without a database lookup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Checksum {
+    /// CRC-32 (IEEE 802.3, as in zlib and GitHub tokens).
+    Crc32,
+    /// CRC-32C (Castagnoli, as in iSCSI and ext4).
+    Crc32c,
+    /// No checksum.
+    None,
+}
+
+impl Checksum {
+    pub const ALL: [Checksum; 3] = [Checksum::Crc32, Checksum::Crc32c, Checksum::None];
+
+    /// Length of the checksum in characters.
+    pub fn width(self) -> usize {
+        match self {
+            Checksum::Crc32 | Checksum::Crc32c => CRC_DIGITS,
+            Checksum::None => 0,
+        }
+    }
+
+    /// The checksum of `data`, as base62 digits zero-padded to [`Self::width`].
+    pub fn compute(self, data: &[u8]) -> String {
+        let value = match self {
+            Checksum::Crc32 => crc32(CRC32_IEEE, data),
+            Checksum::Crc32c => crc32(CRC32_CASTAGNOLI, data),
+            Checksum::None => return String::new(),
+        };
+        let mut value = value as u64;
+        let mut digits = vec![b'0'; self.width()];
+        for digit in digits.iter_mut().rev() {
+            *digit = BASE62[(value % 62) as usize];
+            value /= 62;
+        }
+        digits.iter().map(|&d| d as char).collect()
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Checksum::Crc32 => "crc32",
+            Checksum::Crc32c => "crc32c",
+            Checksum::None => "none",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Checksum::ALL
+            .into_iter()
+            .find(|c| c.to_string() == s.to_ascii_lowercase())
+            .ok_or_else(|| {
+                Error::InvalidApiKey(format!(
+                    "unknown checksum {s} (expected crc32, crc32c or none)"
+                ))
+            })
+    }
+}
+
+/// Bitwise CRC-32 with the given reflected polynomial, initial value and
+/// final XOR of all ones.
+fn crc32(poly: u32, data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// An API key is `<prefix>_<body><checksum>`, or `<body><checksum>` without
+/// a prefix. The body is base62 and the checksum covers everything before
+/// it, prefix included.
+pub struct ApiKeyOptions {
+    pub prefix: String,
+    /// Number of random base62 characters in the body.
+    pub length: usize,
+    pub checksum: Checksum,
+}
+
+impl Default for ApiKeyOptions {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            length: 32,
+            checksum: Checksum::Crc32,
+        }
+    }
+}
+
+impl ApiKeyOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.length == 0 {
+            return Err(Error::InvalidLength);
+        }
+        check_prefix(&self.prefix)
+    }
+
+    /// Entropy in bits of the random body; the prefix and checksum add none.
+    pub fn entropy(&self) -> f64 {
+        entropy::calculate_entropy(BASE62.len(), self.length)
+    }
+
+    fn head(&self) -> String {
+        if self.prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}_", self.prefix)
+        }
+    }
+}
+
+/// Prefixes are matched by secret scanners, so they are kept to letters,
+/// digits and `_`.
+fn check_prefix(prefix: &str) -> Result<()> {
+    if prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(Error::InvalidApiKey(format!(
+            "prefix {prefix} may only contain letters, digits and _"
+        )))
+    }
+}
+
+impl Generator for ApiKeyOptions {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        generate_api_key(self, rng)
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} base62 chars + {} checksum",
+            self.length,
+            self.checksum.to_string().to_uppercase()
+        )
+    }
+
+    fn kind(&self) -> &'static str {
+        "apikey"
+    }
+
+    fn alphabet(&self) -> Option<usize> {
+        Some(BASE62.len())
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        Some(ApiKeyOptions::entropy(self))
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("prefix", self.prefix.clone()),
+            ("length", self.length.to_string()),
+            ("checksum", self.checksum.to_string()),
+        ]
+    }
+}
+
+pub fn generate_api_key<R: RandomSource + ?Sized>(
+    opts: &ApiKeyOptions,
+    rng: &mut R,
+) -> Result<String> {
+    opts.validate()?;
+    let alphanumeric = CharsetBuilder::new().symbols(false).build()?;
+    let mut key = opts.head();
+    key.push_str(&random::generate_password(&alphanumeric, opts.length, rng)?);
+    let checksum = opts.checksum.compute(key.as_bytes());
+    key.push_str(&checksum);
+    Ok(key)
+}
+
+/// Checks the checksum of `key` and, if given, its prefix, without any
+/// lookup.
+pub fn verify_api_key(key: &str, prefix: Option<&str>, checksum: Checksum) -> Result<()> {
+    let invalid = |reason: String| Err(Error::InvalidApiKey(reason));
+    if let Some(prefix) = prefix {
+        check_prefix(prefix)?;
+        let head = format!("{prefix}_");
+        if !key.starts_with(&head) {
+            return invalid(format!("key does not start with {head}"));
+        }
+    }
+    let Some(split) = key.len().checked_sub(checksum.width()) else {
+        return invalid("key is too short".to_string());
+    };
+    if !key.is_char_boundary(split) {
+        return invalid("checksum is not base62".to_string());
+    }
+    let (data, found) = key.split_at(split);
+    if data.is_empty() || data.ends_with('_') {
+        return invalid("key has no body".to_string());
+    }
+    let expected = checksum.compute(data.as_bytes());
+    if found != expected {
+        return invalid(format!("{checksum} checksum mismatch"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc_check_values() {
+        // The "check" values of the CRC catalogue for "123456789"
+        assert_eq!(crc32(CRC32_IEEE, b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(CRC32_CASTAGNOLI, b"123456789"), 0xe306_9283);
+    }
+
+    #[test]
+    fn checksum_is_padded_base62() {
+        assert_eq!(Checksum::Crc32.compute(b""), "000000");
+        // 0xcbf43926 = 3421780262 in base62
+        assert_eq!(Checksum::Crc32.compute(b"123456789"), "3jZRME");
+        assert_eq!(Checksum::None.compute(b"123456789"), "");
+    }
+
+    #[test]
+    fn key_shape() {
+        let opts = ApiKeyOptions {
+            prefix: "acme_live".to_string(),
+            ..Default::default()
+        };
+        let key = generate_api_key(&opts, &mut rand::rng()).unwrap();
+        assert_eq!(key.len(), "acme_live_".len() + 32 + 6);
+        assert!(key.starts_with("acme_live_"));
+        assert!(key[10..].chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(opts.entropy(), 32.0 * 62f64.log2());
+    }
+
+    #[test]
+    fn generated_keys_verify() {
+        for checksum in Checksum::ALL {
+            let opts = ApiKeyOptions {
+                prefix: "test".to_string(),
+                length: 20,
+                checksum,
+            };
+            let key = generate_api_key(&opts, &mut rand::rng()).unwrap();
+            assert_eq!(verify_api_key(&key, Some("test"), checksum), Ok(()));
+            assert_eq!(verify_api_key(&key, None, checksum), Ok(()));
+        }
+    }
+
+    #[test]
+    fn typos_are_caught() {
+        let key = generate_api_key(&ApiKeyOptions::default(), &mut rand::rng()).unwrap();
+        let mut typo: Vec<char> = key.chars().collect();
+        typo[3] = if typo[3] == 'a' { 'b' } else { 'a' };
+        let typo: String = typo.into_iter().collect();
+        assert!(verify_api_key(&typo, None, Checksum::Crc32).is_err());
+        assert!(verify_api_key(&key[..key.len() - 1], None, Checksum::Crc32).is_err());
+        assert!(verify_api_key(&key, Some("acme"), Checksum::Crc32).is_err());
+        assert!(verify_api_key("abc", None, Checksum::Crc32).is_err());
+    }
+
+    #[test]
+    fn invalid_options() {
+        let opts = ApiKeyOptions {
+            prefix: "acme-live".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(opts.validate(), Err(Error::InvalidApiKey(_))));
+        let opts = ApiKeyOptions {
+            length: 0,
+            ..Default::default()
+        };
+        assert_eq!(opts.validate(), Err(Error::InvalidLength));
+        assert!("md5".parse::<Checksum>().is_err());
+    }
+}
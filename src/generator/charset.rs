@@ -1,10 +1,24 @@
-const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
-const DIGITS: &str = "0123456789";
-const SYMBOLS: &str = "!@#$%^&*()-_=+[]{}|;:,.<>?/~`";
+use std::collections::HashSet;
+
+use super::keyboard::{self, Layout};
+use super::unicode::{self, UnicodeClass};
+use crate::entropy;
+use crate::error::{Error, Result};
+
+pub const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+pub const DIGITS: &str = "0123456789";
+pub const SYMBOLS: &str = "!@#$%^&*()-_=+[]{}|;:,.<>?/~`";
 const AMBIGUOUS: &str = "0O1lI|";
-const HEX_CHARS: &str = "0123456789abcdef";
+pub const HEX_CHARS: &str = "0123456789abcdef";
 
+/// Below this probability of a uniform draw meeting the minimums, rejection
+/// sampling would take too long and the request is refused instead.
+pub const MIN_ACCEPTANCE_LOG2: f64 = -20.0;
+
+/// Assembles the alphabet of random passwords: which classes to include,
+/// custom symbols, Unicode classes, exclusions and per-class minimums.
+#[derive(Clone)]
 pub struct CharsetBuilder {
     uppercase: bool,
     lowercase: bool,
@@ -13,6 +27,108 @@ pub struct CharsetBuilder {
     no_ambiguous: bool,
     custom_symbols: Option<String>,
     hex_only: bool,
+    min_uppercase: usize,
+    min_lowercase: usize,
+    min_digits: usize,
+    min_symbols: usize,
+    unicode: Vec<UnicodeClass>,
+    include: Option<String>,
+    exclude: Option<String>,
+    layouts: Vec<Layout>,
+}
+
+/// A group of characters that must appear at least `min` times.
+pub struct CharClass {
+    pub name: &'static str,
+    pub chars: Vec<char>,
+    pub min: usize,
+}
+
+/// The alphabet produced by [`CharsetBuilder`], along with the character
+/// classes it was assembled from and their minimum counts.
+pub struct Charset {
+    chars: Vec<char>,
+    classes: Vec<CharClass>,
+    duplicates: Vec<char>,
+}
+
+impl Charset {
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// Characters that were supplied more than once and dropped so that
+    /// each character of the alphabet is equally likely.
+    pub fn duplicates(&self) -> &[char] {
+        &self.duplicates
+    }
+
+    pub fn classes(&self) -> &[CharClass] {
+        &self.classes
+    }
+
+    /// Returns true if every class minimum is met by `password`.
+    pub fn satisfies(&self, password: &[char]) -> bool {
+        self.classes.iter().all(|class| {
+            class.min == 0
+                || password.iter().filter(|c| class.chars.contains(c)).count() >= class.min
+        })
+    }
+
+    /// Entropy in bits of a uniform choice among all passwords of `length`
+    /// that satisfy the class minimums.
+    pub fn entropy(&self, length: usize) -> f64 {
+        if self.classes.iter().all(|class| class.min == 0) {
+            return entropy::calculate_entropy(self.chars.len(), length);
+        }
+        let sizes: Vec<usize> = self.classes.iter().map(|c| c.chars.len()).collect();
+        let minimums: Vec<usize> = self.classes.iter().map(|c| c.min).collect();
+        entropy::calculate_constrained_entropy(&sizes, &minimums, length)
+    }
+
+    /// Checks that passwords of `length` can be generated with the class
+    /// minimums in a reasonable number of attempts.
+    pub fn check(&self, length: usize) -> Result<()> {
+        if length == 0 {
+            return Err(Error::InvalidLength);
+        }
+        if self.classes.iter().all(|class| class.min == 0) {
+            return Ok(());
+        }
+        for class in &self.classes {
+            if class.min > 0 && class.chars.is_empty() {
+                return Err(Error::MinimumExcluded {
+                    class: class.name,
+                    min: class.min,
+                });
+            }
+        }
+        let required: usize = self.classes.iter().map(|c| c.min).sum();
+        if required > length {
+            return Err(Error::MinimumsExceedLength { required, length });
+        }
+        let unconstrained = entropy::calculate_entropy(self.chars.len(), length);
+        if self.entropy(length) - unconstrained < MIN_ACCEPTANCE_LOG2 {
+            return Err(Error::ConstraintsTooRestrictive { length });
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<char>> for Charset {
+    fn from(chars: Vec<char>) -> Self {
+        Self {
+            chars,
+            classes: Vec::new(),
+            duplicates: Vec::new(),
+        }
+    }
+}
+
+impl Default for CharsetBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CharsetBuilder {
@@ -25,6 +141,14 @@ impl CharsetBuilder {
             no_ambiguous: false,
             custom_symbols: None,
             hex_only: false,
+            min_uppercase: 0,
+            min_lowercase: 0,
+            min_digits: 0,
+            min_symbols: 0,
+            unicode: Vec::new(),
+            include: None,
+            exclude: None,
+            layouts: Vec::new(),
         }
     }
 
@@ -63,37 +187,154 @@ impl CharsetBuilder {
         self
     }
 
-    pub fn build(self) -> Vec<char> {
-        if self.hex_only {
-            return HEX_CHARS.chars().collect();
-        }
+    pub fn min_uppercase(mut self, n: usize) -> Self {
+        self.min_uppercase = n;
+        self
+    }
 
-        let mut chars = String::new();
+    pub fn min_lowercase(mut self, n: usize) -> Self {
+        self.min_lowercase = n;
+        self
+    }
 
-        if self.uppercase {
-            chars.push_str(UPPERCASE);
-        }
-        if self.lowercase {
-            chars.push_str(LOWERCASE);
+    pub fn min_digits(mut self, n: usize) -> Self {
+        self.min_digits = n;
+        self
+    }
+
+    pub fn min_symbols(mut self, n: usize) -> Self {
+        self.min_symbols = n;
+        self
+    }
+
+    /// Adds a Unicode class on top of the ASCII classes.
+    pub fn unicode_class(mut self, class: UnicodeClass) -> Self {
+        self.unicode.push(class);
+        self
+    }
+
+    /// Extra characters to add to the alphabet, even if `no_ambiguous` would
+    /// remove them.
+    pub fn include(mut self, s: Option<String>) -> Self {
+        self.include = s;
+        self
+    }
+
+    /// Characters to remove from the alphabet. Takes precedence over
+    /// everything else, including `include`.
+    pub fn exclude(mut self, s: Option<String>) -> Self {
+        self.exclude = s;
+        self
+    }
+
+    /// Restricts the alphabet to characters typed with the same key and
+    /// Shift state, without dead keys or AltGr, on every given layout.
+    pub fn keyboard_safe(mut self, layouts: Vec<Layout>) -> Self {
+        self.layouts = layouts;
+        self
+    }
+
+    pub fn build(self) -> Result<Charset> {
+        let excluded = match self.exclude {
+            Some(ref exclude) => unicode::parse_set(exclude)?,
+            None => Vec::new(),
+        };
+
+        let layouts = keyboard::tables(&self.layouts);
+        let allowed = |c: char| {
+            !excluded.contains(&c) && (layouts.is_empty() || keyboard::is_safe(c, &layouts))
+        };
+
+        if self.hex_only {
+            let chars: Vec<char> = HEX_CHARS.chars().filter(|&c| allowed(c)).collect();
+            if chars.is_empty() {
+                return Err(Error::EmptyCharset);
+            }
+            return Ok(Charset::from(chars));
         }
-        if self.digits {
-            chars.push_str(DIGITS);
+        if self.custom_symbols.as_deref() == Some("") {
+            return Err(Error::EmptySymbols);
         }
-        if self.symbols {
-            if let Some(ref custom) = self.custom_symbols {
-                chars.push_str(custom);
-            } else {
-                chars.push_str(SYMBOLS);
+
+        let symbols = match self.custom_symbols {
+            Some(ref custom) => unicode::parse_set(custom)?,
+            None => SYMBOLS.chars().collect(),
+        };
+        let mut sets = vec![
+            (
+                "uppercase letters",
+                self.uppercase,
+                UPPERCASE.chars().collect(),
+                self.min_uppercase,
+            ),
+            (
+                "lowercase letters",
+                self.lowercase,
+                LOWERCASE.chars().collect(),
+                self.min_lowercase,
+            ),
+            (
+                "digits",
+                self.digits,
+                DIGITS.chars().collect(),
+                self.min_digits,
+            ),
+            ("symbols", self.symbols, symbols, self.min_symbols),
+        ];
+        for class in &self.unicode {
+            let class_chars = class.chars();
+            if class_chars.is_empty() {
+                return Err(Error::EmptyUnicodeClass(class.to_string()));
             }
+            sets.push((class.name(), true, class_chars, 0));
+        }
+
+        let included = match self.include {
+            Some(ref include) => unicode::parse_set(include)?,
+            None => Vec::new(),
+        };
+        if !included.is_empty() {
+            sets.push(("included characters", true, included, 0));
         }
 
-        if self.no_ambiguous {
-            chars.retain(|c| !AMBIGUOUS.contains(c));
+        let mut chars = Vec::new();
+        let mut classes = Vec::new();
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+
+        for (name, enabled, set, min) in sets {
+            let mut class_chars = Vec::new();
+            if enabled {
+                let skip_ambiguous = self.no_ambiguous && name != "included characters";
+                for c in set {
+                    if !allowed(c) || (skip_ambiguous && AMBIGUOUS.contains(c)) {
+                        continue;
+                    }
+                    if seen.insert(c) {
+                        class_chars.push(c);
+                    } else if !duplicates.contains(&c) {
+                        duplicates.push(c);
+                    }
+                }
+            }
+            chars.extend_from_slice(&class_chars);
+            if enabled || min > 0 {
+                classes.push(CharClass {
+                    name,
+                    chars: class_chars,
+                    min,
+                });
+            }
         }
 
-        let result: Vec<char> = chars.chars().collect();
-        assert!(!result.is_empty(), "Charset cannot be empty");
-        result
+        if chars.is_empty() {
+            return Err(Error::EmptyCharset);
+        }
+        Ok(Charset {
+            chars,
+            classes,
+            duplicates,
+        })
     }
 }
 
@@ -103,7 +344,7 @@ mod tests {
 
     #[test]
     fn default_charset_has_all_types() {
-        let chars = CharsetBuilder::new().build();
+        let chars = CharsetBuilder::new().build().unwrap().chars().to_vec();
         assert!(chars.iter().any(|c| c.is_ascii_uppercase()));
         assert!(chars.iter().any(|c| c.is_ascii_lowercase()));
         assert!(chars.iter().any(|c| c.is_ascii_digit()));
@@ -112,7 +353,12 @@ mod tests {
 
     #[test]
     fn no_ambiguous_removes_chars() {
-        let chars = CharsetBuilder::new().no_ambiguous(true).build();
+        let chars = CharsetBuilder::new()
+            .no_ambiguous(true)
+            .build()
+            .unwrap()
+            .chars()
+            .to_vec();
         assert!(!chars.contains(&'0'));
         assert!(!chars.contains(&'O'));
         assert!(!chars.contains(&'l'));
@@ -125,13 +371,21 @@ mod tests {
             .uppercase(false)
             .lowercase(false)
             .symbols(false)
-            .build();
+            .build()
+            .unwrap()
+            .chars()
+            .to_vec();
         assert!(chars.iter().all(|c| c.is_ascii_digit()));
     }
 
     #[test]
     fn hex_charset() {
-        let chars = CharsetBuilder::new().hex_only(true).build();
+        let chars = CharsetBuilder::new()
+            .hex_only(true)
+            .build()
+            .unwrap()
+            .chars()
+            .to_vec();
         assert_eq!(chars.len(), 16);
         assert!(chars.iter().all(|c| c.is_ascii_hexdigit()));
     }
@@ -143,18 +397,197 @@ mod tests {
             .lowercase(false)
             .digits(false)
             .custom_symbols(Some("!@#".to_string()))
-            .build();
+            .build()
+            .unwrap()
+            .chars()
+            .to_vec();
         assert_eq!(chars, vec!['!', '@', '#']);
     }
 
     #[test]
-    #[should_panic(expected = "Charset cannot be empty")]
-    fn empty_charset_panics() {
-        CharsetBuilder::new()
+    fn empty_charset_is_an_error() {
+        let result = CharsetBuilder::new()
             .uppercase(false)
             .lowercase(false)
             .digits(false)
             .symbols(false)
             .build();
+        assert_eq!(result.err(), Some(Error::EmptyCharset));
+    }
+
+    #[test]
+    fn empty_custom_symbols_is_an_error() {
+        let result = CharsetBuilder::new()
+            .custom_symbols(Some(String::new()))
+            .build();
+        assert_eq!(result.err(), Some(Error::EmptySymbols));
+    }
+
+    #[test]
+    fn duplicates_are_removed_and_reported() {
+        let charset = CharsetBuilder::new()
+            .uppercase(false)
+            .digits(false)
+            .custom_symbols(Some("!!a?".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(charset.chars().len(), 28);
+        assert_eq!(charset.chars().iter().filter(|&&c| c == 'a').count(), 1);
+        assert_eq!(charset.duplicates(), &['!', 'a']);
+
+        let clean = CharsetBuilder::new().build().unwrap();
+        assert!(clean.duplicates().is_empty());
+    }
+
+    #[test]
+    fn exclude_removes_from_every_class() {
+        let chars = CharsetBuilder::new()
+            .exclude(Some("'\"\\ aZ9!".to_string()))
+            .include(Some("' ".to_string()))
+            .build()
+            .unwrap()
+            .chars()
+            .to_vec();
+        for c in ['\'', '"', '\\', ' ', 'a', 'Z', '9', '!'] {
+            assert!(!chars.contains(&c), "{c:?} should be excluded");
+        }
+        assert_eq!(chars.len(), 91 - 4);
+    }
+
+    #[test]
+    fn include_adds_characters() {
+        let chars = CharsetBuilder::new()
+            .symbols(false)
+            .no_ambiguous(true)
+            .include(Some("_0€".to_string()))
+            .build()
+            .unwrap()
+            .chars()
+            .to_vec();
+        assert!(chars.contains(&'_') && chars.contains(&'0') && chars.contains(&'€'));
+        assert!(!chars.contains(&'O'));
+    }
+
+    #[test]
+    fn keyboard_safe_restricts_every_class() {
+        let charset = CharsetBuilder::new()
+            .include(Some("é".to_string()))
+            .keyboard_safe(vec![Layout::Qwerty, Layout::Azerty, Layout::Qwertz])
+            .build()
+            .unwrap();
+        let chars: String = charset.chars().iter().collect();
+        assert_eq!(chars, "BCDEFGHIJKLNOPRSTUVXbcdefghijklnoprstuvx");
+    }
+
+    #[test]
+    fn keyboard_safe_restricts_hex() {
+        let charset = CharsetBuilder::new()
+            .hex_only(true)
+            .keyboard_safe(vec![Layout::Qwerty, Layout::Azerty])
+            .build()
+            .unwrap();
+        let chars: String = charset.chars().iter().collect();
+        assert_eq!(chars, "bcdef");
+    }
+
+    #[test]
+    fn keyboard_safe_minimum_on_removed_class() {
+        let charset = CharsetBuilder::new()
+            .min_digits(1)
+            .keyboard_safe(vec![Layout::Qwerty, Layout::Azerty])
+            .build()
+            .unwrap();
+        assert_eq!(
+            charset.check(16),
+            Err(Error::MinimumExcluded {
+                class: "digits",
+                min: 1
+            })
+        );
+    }
+
+    #[test]
+    fn unicode_classes_are_added() {
+        let charset = CharsetBuilder::new()
+            .unicode_class(UnicodeClass::Greek)
+            .unicode_class(UnicodeClass::Emoji)
+            .build()
+            .unwrap();
+        assert!(charset.chars().contains(&'λ'));
+        assert!(charset.chars().contains(&'😀'));
+        assert_eq!(charset.chars().len(), 91 + 49 + 80);
+    }
+
+    #[test]
+    fn empty_unicode_range_is_an_error() {
+        let result = CharsetBuilder::new()
+            .unicode_class(UnicodeClass::Range(0x300, 0x36F))
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(Error::EmptyUnicodeClass("U+0300-U+036F".to_string()))
+        );
+    }
+
+    #[test]
+    fn minimums_are_recorded_per_class() {
+        let charset = CharsetBuilder::new()
+            .min_digits(2)
+            .min_symbols(1)
+            .build()
+            .unwrap();
+        let digits = charset.classes.iter().find(|c| c.name == "digits").unwrap();
+        assert_eq!(digits.min, 2);
+        assert_eq!(digits.chars.len(), 10);
+        assert!(charset.satisfies(&['a', '1', '2', '!']));
+        assert!(!charset.satisfies(&['a', 'b', '2', '!']));
+    }
+
+    #[test]
+    fn minimums_reduce_entropy() {
+        let plain = CharsetBuilder::new().build().unwrap();
+        let constrained = CharsetBuilder::new()
+            .min_digits(2)
+            .min_symbols(1)
+            .build()
+            .unwrap();
+        assert!(constrained.entropy(16) < plain.entropy(16));
+        assert!(constrained.entropy(16) > plain.entropy(16) - 1.0);
+    }
+
+    #[test]
+    fn check_rejects_unsatisfiable_minimums() {
+        let charset = CharsetBuilder::new()
+            .min_digits(3)
+            .min_symbols(3)
+            .build()
+            .unwrap();
+        assert!(charset.check(6).is_ok());
+        assert_eq!(
+            charset.check(5),
+            Err(Error::MinimumsExceedLength {
+                required: 6,
+                length: 5
+            })
+        );
+
+        let charset = CharsetBuilder::new()
+            .digits(false)
+            .min_digits(1)
+            .build()
+            .unwrap();
+        assert_eq!(
+            charset.check(16),
+            Err(Error::MinimumExcluded {
+                class: "digits",
+                min: 1
+            })
+        );
+
+        let charset = CharsetBuilder::new().min_symbols(40).build().unwrap();
+        assert_eq!(
+            charset.check(40),
+            Err(Error::ConstraintsTooRestrictive { length: 40 })
+        );
     }
 }
@@ -0,0 +1,701 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Generator;
+use super::token::{BASE32_CROCKFORD, BASE62, Encoding};
+use crate::entropy;
+use crate::error::{Error, Result};
+use crate::rng::{self, RandomSource};
+
+/// The NanoID default alphabet, URL-safe.
+pub const NANOID_ALPHABET: &str =
+    "_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const NANOID_SIZE: usize = 21;
+/// KSUID timestamps count seconds from 2014-05-13T16:53:20Z.
+const KSUID_EPOCH: u64 = 1_400_000_000;
+/// UUIDv7 and ULID timestamps are 48-bit milliseconds.
+const MAX_MILLIS: u64 = (1 << 48) - 1;
+
+/// The identifier formats of `pw id`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdFormat {
+    /// RFC 9562 UUID version 4: 122 random bits.
+    Uuid4,
+    /// RFC 9562 UUID version 7: 48-bit Unix milliseconds, then 74 random bits.
+    Uuid7,
+    /// 48-bit Unix milliseconds and 80 random bits in Crockford's base32.
+    Ulid,
+    /// Random characters from a URL-safe alphabet, 21 by default.
+    NanoId,
+    /// 32-bit seconds since the KSUID epoch and 128 random bits in base62.
+    Ksuid,
+}
+
+impl IdFormat {
+    pub const ALL: [IdFormat; 5] = [
+        IdFormat::Uuid4,
+        IdFormat::Uuid7,
+        IdFormat::Ulid,
+        IdFormat::NanoId,
+        IdFormat::Ksuid,
+    ];
+
+    fn is_uuid(self) -> bool {
+        matches!(self, IdFormat::Uuid4 | IdFormat::Uuid7)
+    }
+
+    fn has_timestamp(self) -> bool {
+        matches!(self, IdFormat::Uuid7 | IdFormat::Ulid | IdFormat::Ksuid)
+    }
+}
+
+impl fmt::Display for IdFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IdFormat::Uuid4 => "uuid4",
+            IdFormat::Uuid7 => "uuid7",
+            IdFormat::Ulid => "ulid",
+            IdFormat::NanoId => "nanoid",
+            IdFormat::Ksuid => "ksuid",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for IdFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.to_ascii_lowercase();
+        if s == "uuid" {
+            return Ok(IdFormat::Uuid4);
+        }
+        IdFormat::ALL
+            .into_iter()
+            .find(|f| f.to_string() == s)
+            .ok_or_else(|| {
+                Error::InvalidId(format!(
+                    "unknown format {s} (expected uuid4, uuid7, ulid, nanoid or ksuid)"
+                ))
+            })
+    }
+}
+
+pub struct IdOptions {
+    pub format: IdFormat,
+    /// Render UUIDs with uppercase hex digits.
+    pub uppercase: bool,
+    /// Wrap UUIDs in braces, as the Windows registry does.
+    pub braced: bool,
+    /// Prefix UUIDs with `urn:uuid:` (RFC 9562 section 4).
+    pub urn: bool,
+    /// NanoID alphabet, [`NANOID_ALPHABET`] if not set.
+    pub alphabet: Option<String>,
+    /// NanoID length, 21 if not set.
+    pub size: Option<usize>,
+    /// Unix time in milliseconds to embed instead of the current time.
+    pub time_ms: Option<u64>,
+}
+
+impl Default for IdOptions {
+    fn default() -> Self {
+        Self {
+            format: IdFormat::Uuid4,
+            uppercase: false,
+            braced: false,
+            urn: false,
+            alphabet: None,
+            size: None,
+            time_ms: None,
+        }
+    }
+}
+
+impl IdOptions {
+    fn alphabet(&self) -> Vec<char> {
+        self.alphabet
+            .as_deref()
+            .unwrap_or(NANOID_ALPHABET)
+            .chars()
+            .collect()
+    }
+
+    /// Rejects options that do not apply to the format or cannot be met.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| Err(Error::InvalidId(reason.to_string()));
+        if !self.format.is_uuid() && (self.uppercase || self.braced || self.urn) {
+            return invalid("--uppercase, --braced and --urn only apply to UUIDs");
+        }
+        if self.braced && self.urn {
+            return invalid("a UUID cannot be both braced and a URN");
+        }
+        if self.format != IdFormat::NanoId && (self.alphabet.is_some() || self.size.is_some()) {
+            return invalid("--alphabet and --size only apply to NanoIDs");
+        }
+        if self.size == Some(0) {
+            return Err(Error::InvalidLength);
+        }
+        let alphabet = self.alphabet();
+        if alphabet.len() < 2 {
+            return invalid("the NanoID alphabet needs at least 2 characters");
+        }
+        let mut sorted = alphabet.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != alphabet.len() {
+            return invalid("the NanoID alphabet has duplicate characters");
+        }
+        match self.time_ms {
+            Some(_) if !self.format.has_timestamp() => {
+                invalid("--time only applies to uuid7, ulid and ksuid")
+            }
+            Some(ms) if ms > MAX_MILLIS => invalid("--time does not fit in 48 bits"),
+            Some(ms) if self.format == IdFormat::Ksuid => ksuid_seconds(ms).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Bits of randomness in each ID; the timestamp is not counted.
+    pub fn entropy(&self) -> f64 {
+        match self.format {
+            IdFormat::Uuid4 => 122.0,
+            IdFormat::Uuid7 => 74.0,
+            IdFormat::Ulid => 80.0,
+            IdFormat::NanoId => {
+                entropy::calculate_entropy(self.alphabet().len(), self.size.unwrap_or(NANOID_SIZE))
+            }
+            IdFormat::Ksuid => 128.0,
+        }
+    }
+
+    fn render_uuid(&self, bytes: &[u8; 16]) -> String {
+        let hex = Encoding::HexLower.encode(bytes);
+        let mut uuid = format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        );
+        if self.uppercase {
+            uuid.make_ascii_uppercase();
+        }
+        if self.braced {
+            format!("{{{uuid}}}")
+        } else if self.urn {
+            format!("urn:uuid:{uuid}")
+        } else {
+            uuid
+        }
+    }
+}
+
+impl Generator for IdOptions {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        generate_id(self, rng)
+    }
+
+    fn describe(&self) -> String {
+        match self.format {
+            IdFormat::Uuid4 => "UUID v4".to_string(),
+            IdFormat::Uuid7 => "UUID v7, time-ordered".to_string(),
+            IdFormat::Ulid => "ULID, time-ordered".to_string(),
+            IdFormat::NanoId => format!("NanoID, {} chars", self.size.unwrap_or(NANOID_SIZE)),
+            IdFormat::Ksuid => "KSUID, time-ordered".to_string(),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "id"
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        Some(IdOptions::entropy(self))
+    }
+
+    fn is_deterministic(&self) -> bool {
+        match self.format {
+            IdFormat::Uuid7 | IdFormat::Ulid | IdFormat::Ksuid => self.time_ms.is_some(),
+            IdFormat::Uuid4 | IdFormat::NanoId => true,
+        }
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        let mut parameters = vec![("format", self.format.to_string())];
+        if self.format.is_uuid() {
+            parameters.push(("uppercase", self.uppercase.to_string()));
+            parameters.push(("braced", self.braced.to_string()));
+            parameters.push(("urn", self.urn.to_string()));
+        }
+        if self.format == IdFormat::NanoId {
+            parameters.push(("alphabet", self.alphabet().into_iter().collect()));
+            parameters.push(("size", self.size.unwrap_or(NANOID_SIZE).to_string()));
+        }
+        if let Some(ms) = self.time_ms {
+            parameters.push(("time_ms", ms.to_string()));
+        }
+        parameters
+    }
+}
+
+/// Generates one ID. Time-ordered formats embed `opts.time_ms` or the
+/// current time; IDs generated within the same millisecond are not ordered
+/// among themselves.
+pub fn generate_id<R: RandomSource + ?Sized>(opts: &IdOptions, rng: &mut R) -> Result<String> {
+    opts.validate()?;
+    let now = || match opts.time_ms {
+        Some(ms) => Ok(ms),
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .map_err(|_| Error::InvalidId("the system clock is before 1970".to_string())),
+    };
+    match opts.format {
+        IdFormat::Uuid4 => {
+            let mut bytes = [0u8; 16];
+            rng.fill(&mut bytes)?;
+            Ok(opts.render_uuid(&set_uuid_version(bytes, 4)))
+        }
+        IdFormat::Uuid7 => {
+            let bytes = timestamped(now()?, rng)?;
+            Ok(opts.render_uuid(&set_uuid_version(bytes, 7)))
+        }
+        IdFormat::Ulid => {
+            let value = u128::from_be_bytes(timestamped(now()?, rng)?);
+            // 26 characters hold 130 bits, so the first one only has 3
+            Ok((0..26)
+                .map(|i| BASE32_CROCKFORD[((value >> (125 - 5 * i)) & 0x1f) as usize] as char)
+                .collect())
+        }
+        IdFormat::NanoId => {
+            let alphabet = opts.alphabet();
+            (0..opts.size.unwrap_or(NANOID_SIZE))
+                .map(|_| Ok(alphabet[rng::below(rng, alphabet.len())?]))
+                .collect()
+        }
+        IdFormat::Ksuid => {
+            let mut bytes = [0u8; 20];
+            bytes[..4].copy_from_slice(&ksuid_seconds(now()?)?.to_be_bytes());
+            rng.fill(&mut bytes[4..])?;
+            Ok(encode_base62(&bytes))
+        }
+    }
+}
+
+/// 48-bit big-endian milliseconds followed by 10 random bytes, the layout
+/// shared by UUIDv7 and ULID.
+fn timestamped<R: RandomSource + ?Sized>(ms: u64, rng: &mut R) -> Result<[u8; 16]> {
+    if ms > MAX_MILLIS {
+        return Err(Error::InvalidId("time does not fit in 48 bits".to_string()));
+    }
+    let mut bytes = [0u8; 16];
+    bytes[..6].copy_from_slice(&ms.to_be_bytes()[2..]);
+    rng.fill(&mut bytes[6..])?;
+    Ok(bytes)
+}
+
+fn set_uuid_version(mut bytes: [u8; 16], version: u8) -> [u8; 16] {
+    bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+    // RFC 9562 variant, 0b10
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    bytes
+}
+
+fn ksuid_seconds(ms: u64) -> Result<u32> {
+    (ms / 1000)
+        .checked_sub(KSUID_EPOCH)
+        .and_then(|s| u32::try_from(s).ok())
+        .ok_or_else(|| {
+            Error::InvalidId("time is outside the KSUID range (2014 to 2150)".to_string())
+        })
+}
+
+/// 20 bytes as a 27-digit big-endian base62 number, zero-padded.
+fn encode_base62(bytes: &[u8; 20]) -> String {
+    let mut number = *bytes;
+    let mut digits = [b'0'; 27];
+    for digit in digits.iter_mut().rev() {
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 62) as u8;
+            remainder = acc % 62;
+        }
+        *digit = BASE62[remainder as usize];
+    }
+    digits.iter().map(|&d| d as char).collect()
+}
+
+fn decode_base62(s: &str) -> Option<[u8; 20]> {
+    let mut number = [0u8; 20];
+    for c in s.bytes() {
+        let mut carry = BASE62.iter().position(|&b| b == c)? as u32;
+        for byte in number.iter_mut().rev() {
+            let acc = *byte as u32 * 62 + carry;
+            *byte = acc as u8;
+            carry = acc >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(number)
+}
+
+/// What `pw id --inspect` found in an ID.
+#[derive(Debug, PartialEq)]
+pub struct IdInfo {
+    /// The format, with the UUID version if any, e.g. "UUID version 7".
+    pub format: String,
+    /// Embedded Unix time in milliseconds.
+    pub timestamp_ms: Option<u64>,
+    /// The random portion in hex, including any version and variant bits
+    /// that share its bytes.
+    pub random: Option<String>,
+    /// How many bits of the random portion are actually random.
+    pub random_bits: Option<u32>,
+}
+
+/// Recognises a UUID (plain, braced, URN or bare hex), ULID, KSUID or
+/// default-alphabet NanoID by its shape and decodes its fields.
+pub fn inspect(id: &str) -> Result<IdInfo> {
+    let id = id.trim();
+    let uuid = id
+        .get(..9)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("urn:uuid:"))
+        .map_or(id, |_| &id[9..]);
+    let uuid = uuid
+        .strip_prefix('{')
+        .and_then(|u| u.strip_suffix('}'))
+        .unwrap_or(uuid);
+    if let Some(bytes) = parse_uuid(uuid) {
+        return Ok(inspect_uuid(&bytes));
+    }
+    match id.len() {
+        26 => inspect_ulid(id),
+        27 => inspect_ksuid(id),
+        21 if id.chars().all(|c| NANOID_ALPHABET.contains(c)) => Ok(IdInfo {
+            format: "NanoID".to_string(),
+            timestamp_ms: None,
+            random: None,
+            random_bits: Some(entropy::calculate_entropy(NANOID_ALPHABET.len(), 21) as u32),
+        }),
+        _ => Err(Error::InvalidId(format!(
+            "{id} is not a UUID, ULID, KSUID or NanoID"
+        ))),
+    }
+}
+
+fn parse_uuid(s: &str) -> Option<[u8; 16]> {
+    let hex: String = match s.len() {
+        36 if [8, 13, 18, 23].iter().all(|&i| s.as_bytes()[i] == b'-') => {
+            s.chars().filter(|&c| c != '-').collect()
+        }
+        32 => s.to_string(),
+        _ => return None,
+    };
+    if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn inspect_uuid(bytes: &[u8; 16]) -> IdInfo {
+    let version = bytes[6] >> 4;
+    let (timestamp_ms, random, random_bits) = match version {
+        4 => (None, Some(&bytes[..]), Some(122)),
+        7 => {
+            let mut ms = [0u8; 8];
+            ms[2..].copy_from_slice(&bytes[..6]);
+            (Some(u64::from_be_bytes(ms)), Some(&bytes[6..]), Some(74))
+        }
+        _ => (None, None, None),
+    };
+    IdInfo {
+        format: format!("UUID version {version}"),
+        timestamp_ms,
+        random: random.map(|r| Encoding::HexLower.encode(r)),
+        random_bits,
+    }
+}
+
+fn inspect_ulid(id: &str) -> Result<IdInfo> {
+    let mut value = 0u128;
+    for (i, c) in id.chars().enumerate() {
+        // Crockford decoding is case-insensitive and reads I, L as 1 and O as 0
+        let c = match c.to_ascii_uppercase() {
+            'I' | 'L' => '1',
+            'O' => '0',
+            c => c,
+        };
+        let digit = BASE32_CROCKFORD
+            .iter()
+            .position(|&b| b as char == c)
+            .ok_or_else(|| Error::InvalidId(format!("{id} is not a ULID: bad character {c}")))?;
+        if i == 0 && digit > 7 {
+            return Err(Error::InvalidId(format!(
+                "{id} is not a ULID: overflows 128 bits"
+            )));
+        }
+        value = (value << 5) | digit as u128;
+    }
+    let bytes = value.to_be_bytes();
+    let mut ms = [0u8; 8];
+    ms[2..].copy_from_slice(&bytes[..6]);
+    Ok(IdInfo {
+        format: "ULID".to_string(),
+        timestamp_ms: Some(u64::from_be_bytes(ms)),
+        random: Some(Encoding::HexLower.encode(&bytes[6..])),
+        random_bits: Some(80),
+    })
+}
+
+fn inspect_ksuid(id: &str) -> Result<IdInfo> {
+    let bytes =
+        decode_base62(id).ok_or_else(|| Error::InvalidId(format!("{id} is not a KSUID")))?;
+    let seconds = u32::from_be_bytes(bytes[..4].try_into().expect("4 bytes"));
+    Ok(IdInfo {
+        format: "KSUID".to_string(),
+        timestamp_ms: Some((KSUID_EPOCH + seconds as u64) * 1000),
+        random: Some(Encoding::HexLower.encode(&bytes[4..])),
+        random_bits: Some(128),
+    })
+}
+
+/// Unix milliseconds as an RFC 3339 UTC timestamp.
+pub fn format_timestamp(ms: u64) -> String {
+    let days = ms / 86_400_000;
+    let millis = ms % 86_400_000;
+    // Civil date from days since 1970-01-01, after Howard Hinnant's
+    // days_from_civil inverse, shifted so that eras start on March 1st
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: IdFormat) -> IdOptions {
+        IdOptions {
+            format,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn uuid_format() {
+        let uuid = generate_id(&options(IdFormat::Uuid4), &mut rand::rng()).unwrap();
+        assert_eq!(uuid.len(), 36);
+        let parts: Vec<&str> = uuid.split('-').collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts[2].starts_with('4'));
+        assert!(matches!(
+            parts[3].chars().next(),
+            Some('8' | '9' | 'a' | 'b')
+        ));
+    }
+
+    #[test]
+    fn uuid_rendering() {
+        let bytes = set_uuid_version([0xab; 16], 4);
+        let render = |uppercase, braced, urn| {
+            IdOptions {
+                uppercase,
+                braced,
+                urn,
+                ..Default::default()
+            }
+            .render_uuid(&bytes)
+        };
+        assert_eq!(
+            render(false, false, false),
+            "abababab-abab-4bab-abab-abababababab"
+        );
+        assert_eq!(
+            render(true, true, false),
+            "{ABABABAB-ABAB-4BAB-ABAB-ABABABABABAB}"
+        );
+        assert_eq!(
+            render(false, false, true),
+            "urn:uuid:abababab-abab-4bab-abab-abababababab"
+        );
+    }
+
+    #[test]
+    fn uuid7_round_trip() {
+        let opts = IdOptions {
+            format: IdFormat::Uuid7,
+            time_ms: Some(1_645_557_742_000),
+            ..Default::default()
+        };
+        let uuid = generate_id(&opts, &mut rand::rng()).unwrap();
+        assert!(uuid.starts_with("017f22e2-79b0-7"));
+        let info = inspect(&uuid).unwrap();
+        assert_eq!(info.format, "UUID version 7");
+        assert_eq!(info.timestamp_ms, Some(1_645_557_742_000));
+    }
+
+    #[test]
+    fn rfc9562_uuid7_example() {
+        // RFC 9562 appendix A.6
+        let info = inspect("017F22E2-79B0-7CC3-98C4-DC0C0C07398F").unwrap();
+        assert_eq!(info.timestamp_ms, Some(0x017F22E279B0));
+        assert_eq!(info.random.as_deref(), Some("7cc398c4dc0c0c07398f"));
+        assert_eq!(
+            format_timestamp(info.timestamp_ms.unwrap()),
+            "2022-02-22T19:22:22.000Z"
+        );
+    }
+
+    #[test]
+    fn ulid_spec_example() {
+        // From the ulid/javascript README: ulid(1469918176385)
+        let info = inspect("01ARYZ6S41TSV4RRFFQ69G5FAV").unwrap();
+        assert_eq!(info.format, "ULID");
+        assert_eq!(info.timestamp_ms, Some(1_469_918_176_385));
+        assert!(inspect("81ARYZ6S41TSV4RRFFQ69G5FAV").is_err());
+    }
+
+    #[test]
+    fn ulid_round_trip() {
+        let opts = IdOptions {
+            format: IdFormat::Ulid,
+            time_ms: Some(1_469_918_176_385),
+            ..Default::default()
+        };
+        let ulid = generate_id(&opts, &mut rand::rng()).unwrap();
+        assert_eq!(ulid.len(), 26);
+        assert!(ulid.starts_with("01ARYZ6S41"));
+        assert_eq!(inspect(&ulid).unwrap().timestamp_ms, opts.time_ms);
+    }
+
+    #[test]
+    fn ksuid_reference_example() {
+        // From the segmentio/ksuid README
+        let info = inspect("0ujtsYcgvSTl8PAuAdqWYSMnLOv").unwrap();
+        assert_eq!(info.format, "KSUID");
+        assert_eq!(info.timestamp_ms, Some(1_507_608_047_000));
+        assert_eq!(
+            info.random.as_deref(),
+            Some("b5a1cd34b5f99d1154fb6853345c9735")
+        );
+        let mut raw = [0u8; 20];
+        raw[..4].copy_from_slice(&107_608_047u32.to_be_bytes());
+        raw[4..].copy_from_slice(&[
+            0xb5, 0xa1, 0xcd, 0x34, 0xb5, 0xf9, 0x9d, 0x11, 0x54, 0xfb, 0x68, 0x53, 0x34, 0x5c,
+            0x97, 0x35,
+        ]);
+        assert_eq!(encode_base62(&raw), "0ujtsYcgvSTl8PAuAdqWYSMnLOv");
+        assert_eq!(encode_base62(&[0xff; 20]), "aWgEPTl1tmebfsQzFP4bxwgy80V");
+        assert!(inspect("aWgEPTl1tmebfsQzFP4bxwgy80W").is_err());
+    }
+
+    #[test]
+    fn nanoid_alphabet_and_size() {
+        let opts = IdOptions {
+            format: IdFormat::NanoId,
+            alphabet: Some("abc".to_string()),
+            size: Some(10),
+            ..Default::default()
+        };
+        let id = generate_id(&opts, &mut rand::rng()).unwrap();
+        assert_eq!(id.len(), 10);
+        assert!(id.chars().all(|c| "abc".contains(c)));
+        let default = generate_id(&options(IdFormat::NanoId), &mut rand::rng()).unwrap();
+        assert_eq!(default.len(), 21);
+        assert_eq!(inspect(&default).unwrap().format, "NanoID");
+        assert_eq!(options(IdFormat::NanoId).entropy(), 126.0);
+    }
+
+    #[test]
+    fn invalid_options() {
+        let invalid = [
+            IdOptions {
+                format: IdFormat::Ulid,
+                uppercase: true,
+                ..Default::default()
+            },
+            IdOptions {
+                braced: true,
+                urn: true,
+                ..Default::default()
+            },
+            IdOptions {
+                size: Some(8),
+                ..Default::default()
+            },
+            IdOptions {
+                format: IdFormat::NanoId,
+                alphabet: Some("aa".to_string()),
+                ..Default::default()
+            },
+            IdOptions {
+                time_ms: Some(0),
+                ..Default::default()
+            },
+            IdOptions {
+                format: IdFormat::Ksuid,
+                time_ms: Some(0),
+                ..Default::default()
+            },
+        ];
+        for opts in invalid {
+            assert!(opts.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn parse_formats() {
+        for format in IdFormat::ALL {
+            assert_eq!(format.to_string().parse::<IdFormat>(), Ok(format));
+        }
+        assert_eq!("UUID".parse::<IdFormat>(), Ok(IdFormat::Uuid4));
+        assert!("snowflake".parse::<IdFormat>().is_err());
+    }
+
+    #[test]
+    fn inspect_accepts_uuid_renderings() {
+        for uuid in [
+            "{017f22e2-79b0-7cc3-98c4-dc0c0c07398f}",
+            "urn:uuid:017f22e2-79b0-7cc3-98c4-dc0c0c07398f",
+            "017f22e279b07cc398c4dc0c0c07398f",
+        ] {
+            assert_eq!(inspect(uuid).unwrap().format, "UUID version 7");
+        }
+        assert!(inspect("not-an-id").is_err());
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_timestamp(951_782_400_000),
+            "2000-02-29T00:00:00.000Z"
+        );
+        assert_eq!(
+            format_timestamp(1_469_918_176_385),
+            "2016-07-30T22:36:16.385Z"
+        );
+    }
+}
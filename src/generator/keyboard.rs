@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// A keyboard layout whose key table is embedded in the binary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    Qwerty,
+    Azerty,
+    Qwertz,
+}
+
+/// Where a character is typed: the physical key, and whether Shift is held.
+type Position = (&'static str, bool);
+
+impl Layout {
+    fn table(self) -> &'static str {
+        match self {
+            Layout::Qwerty => include_str!("../../data/keyboards/qwerty.txt"),
+            Layout::Azerty => include_str!("../../data/keyboards/azerty.txt"),
+            Layout::Qwertz => include_str!("../../data/keyboards/qwertz.txt"),
+        }
+    }
+
+    /// Characters typed with a single key, with or without Shift. Dead keys,
+    /// AltGr levels and keys missing from the table are left out.
+    fn positions(self) -> HashMap<char, Position> {
+        let mut positions = HashMap::new();
+        for line in self.table().lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // Format: "AD01 q Q"
+            let mut fields = line.split_whitespace();
+            let Some(key) = fields.next() else { continue };
+            for (level, shift) in fields.zip([false, true]) {
+                let mut chars = level.chars();
+                if let (Some(c), None) = (chars.next(), chars.next()) {
+                    positions.insert(c, (key, shift));
+                }
+            }
+        }
+        positions
+    }
+}
+
+impl FromStr for Layout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "qwerty" | "us" => Ok(Layout::Qwerty),
+            "azerty" | "fr" => Ok(Layout::Azerty),
+            "qwertz" | "de" => Ok(Layout::Qwertz),
+            _ => Err(Error::UnknownLayout(s.to_string())),
+        }
+    }
+}
+
+/// Returns true if `c` is typed with the same key and Shift state on every
+/// layout in `layouts`.
+pub fn is_safe(c: char, layouts: &[HashMap<char, Position>]) -> bool {
+    let mut positions = layouts.iter().map(|layout| layout.get(&c));
+    match positions.next() {
+        Some(Some(first)) => positions.all(|p| p == Some(first)),
+        _ => false,
+    }
+}
+
+/// The position tables of `layouts`, for use with [`is_safe`].
+pub fn tables(layouts: &[Layout]) -> Vec<HashMap<char, Position>> {
+    layouts.iter().map(|layout| layout.positions()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn safe(layouts: &[Layout]) -> String {
+        let tables = tables(layouts);
+        (' '..='~').filter(|&c| is_safe(c, &tables)).collect()
+    }
+
+    #[test]
+    fn tables_cover_letters() {
+        for layout in [Layout::Qwerty, Layout::Azerty, Layout::Qwertz] {
+            let positions = layout.positions();
+            assert!(
+                ('a'..='z')
+                    .chain('A'..='Z')
+                    .all(|c| positions.contains_key(&c)),
+                "{layout:?} is missing letters"
+            );
+        }
+    }
+
+    #[test]
+    fn single_layout_is_all_of_its_characters() {
+        let qwerty = safe(&[Layout::Qwerty]);
+        assert_eq!(qwerty.len(), 94);
+    }
+
+    #[test]
+    fn azerty_swaps_letters_and_shifts_digits() {
+        let safe = safe(&[Layout::Qwerty, Layout::Azerty]);
+        for c in "aqzwmAQZWM0123456789".chars() {
+            assert!(!safe.contains(c), "{c} should not be safe");
+        }
+        assert!(safe.contains('=') && safe.contains('+'));
+        assert!(safe.contains('e') && safe.contains('Y'));
+    }
+
+    #[test]
+    fn qwertz_swaps_y_and_z() {
+        let safe = safe(&[Layout::Qwerty, Layout::Qwertz]);
+        assert!(!safe.contains('y') && !safe.contains('z'));
+        assert!(safe.contains('1') && safe.contains('!'));
+        assert!(!safe.contains('@') && !safe.contains('#'));
+    }
+
+    #[test]
+    fn dead_keys_are_never_safe() {
+        // ^ is a dead key on QWERTZ, on the key where QWERTY has `
+        assert!(!safe(&[Layout::Qwertz]).contains('^'));
+    }
+
+    #[test]
+    fn parse_layouts() {
+        assert_eq!("AZERTY".parse::<Layout>(), Ok(Layout::Azerty));
+        assert_eq!("de".parse::<Layout>(), Ok(Layout::Qwertz));
+        assert!("dvorak".parse::<Layout>().is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use super::Generator;
+use super::charset::{DIGITS, HEX_CHARS, LOWERCASE, SYMBOLS, UPPERCASE};
+use crate::entropy;
+use crate::error::{Error, Result};
+use crate::rng::{self, RandomSource};
+
+/// A parsed mask: one set of candidate characters per output position.
+///
+/// Placeholders follow hashcat:
+/// `?l` lowercase, `?u` uppercase, `?d` digits, `?s` symbols,
+/// `?a` all of the above, `?h`/`?H` lower/upper hex digits,
+/// `?1`..`?4` custom sets, and `??` a literal `?`.
+/// Any other character is copied as is.
+pub struct Mask {
+    pattern: String,
+    positions: Vec<Vec<char>>,
+}
+
+impl Mask {
+    /// Parses `pattern`. `custom` holds the definitions of `?1`..`?4`, which
+    /// may themselves use the built-in placeholders (e.g. `?l?d`).
+    pub fn parse(pattern: &str, custom: &[Option<String>; 4]) -> Result<Self> {
+        let mut custom_sets: [Option<Vec<char>>; 4] = Default::default();
+        for (i, def) in custom.iter().enumerate() {
+            if let Some(def) = def {
+                let set = expand_custom(def)?;
+                if set.is_empty() {
+                    return Err(Error::InvalidMask(format!(
+                        "custom set ?{} is empty",
+                        i + 1
+                    )));
+                }
+                custom_sets[i] = Some(set);
+            }
+        }
+
+        let mut positions = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '?' {
+                positions.push(vec![c]);
+                continue;
+            }
+            let Some(p) = chars.next() else {
+                return Err(Error::InvalidMask("pattern ends with '?'".to_string()));
+            };
+            let set = match p {
+                '1'..='4' => {
+                    let idx = p as usize - '1' as usize;
+                    custom_sets[idx].clone().ok_or_else(|| {
+                        Error::InvalidMask(format!("custom set ?{p} is not defined"))
+                    })?
+                }
+                _ => builtin(p)?,
+            };
+            positions.push(set);
+        }
+
+        if positions.is_empty() {
+            return Err(Error::InvalidMask("pattern is empty".to_string()));
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            positions,
+        })
+    }
+
+    /// Entropy in bits: the sum over positions of log2 of the set size.
+    pub fn entropy(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|set| entropy::calculate_entropy(set.len(), 1))
+            .sum()
+    }
+}
+
+impl Generator for Mask {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        generate_mask(self, rng)
+    }
+
+    fn describe(&self) -> String {
+        format!("Mask {}", self.pattern)
+    }
+
+    fn kind(&self) -> &'static str {
+        "mask"
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        Some(Mask::entropy(self))
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![("pattern", self.pattern.clone())]
+    }
+}
+
+pub fn generate_mask<R: RandomSource + ?Sized>(mask: &Mask, rng: &mut R) -> Result<String> {
+    mask.positions
+        .iter()
+        .map(|set| Ok(set[rng::below(rng, set.len())?]))
+        .collect()
+}
+
+fn builtin(p: char) -> Result<Vec<char>> {
+    let set: String = match p {
+        'l' => LOWERCASE.to_string(),
+        'u' => UPPERCASE.to_string(),
+        'd' => DIGITS.to_string(),
+        's' => SYMBOLS.to_string(),
+        'a' => [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS].concat(),
+        'h' => HEX_CHARS.to_string(),
+        'H' => HEX_CHARS.to_uppercase(),
+        '?' => "?".to_string(),
+        _ => return Err(Error::InvalidMask(format!("unknown placeholder ?{p}"))),
+    };
+    Ok(set.chars().collect())
+}
+
+/// Expands a custom set definition, deduplicating so that no character is
+/// more likely than another.
+fn expand_custom(def: &str) -> Result<Vec<char>> {
+    let mut set: Vec<char> = Vec::new();
+    let mut chars = def.chars();
+    while let Some(c) = chars.next() {
+        let expanded = if c == '?' {
+            match chars.next() {
+                Some(p) => builtin(p)?,
+                None => vec!['?'],
+            }
+        } else {
+            vec![c]
+        };
+        for c in expanded {
+            if !set.contains(&c) {
+                set.push(c);
+            }
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_custom() -> [Option<String>; 4] {
+        Default::default()
+    }
+
+    #[test]
+    fn mask_shape() {
+        let mask = Mask::parse("?u?l?l?d-?d?d?d?d", &no_custom()).unwrap();
+        let pw: Vec<char> = generate_mask(&mask, &mut rand::rng())
+            .unwrap()
+            .chars()
+            .collect();
+        assert_eq!(pw.len(), 9);
+        assert!(pw[0].is_ascii_uppercase());
+        assert!(pw[1].is_ascii_lowercase() && pw[2].is_ascii_lowercase());
+        assert!(pw[3].is_ascii_digit());
+        assert_eq!(pw[4], '-');
+        assert!(pw[5..].iter().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn mask_entropy() {
+        let mask = Mask::parse("?d?d?d?d", &no_custom()).unwrap();
+        assert!((mask.entropy() - 4.0 * 10f64.log2()).abs() < 1e-9);
+
+        let literal = Mask::parse("abc-??", &no_custom()).unwrap();
+        assert_eq!(literal.entropy(), 0.0);
+        assert_eq!(generate_mask(&literal, &mut rand::rng()).unwrap(), "abc-?");
+    }
+
+    #[test]
+    fn custom_sets_expand_and_deduplicate() {
+        let custom = [Some("?dabca".to_string()), None, None, None];
+        let mask = Mask::parse("?1", &custom).unwrap();
+        assert_eq!(mask.positions[0].len(), 13);
+    }
+
+    #[test]
+    fn invalid_masks() {
+        assert!(Mask::parse("", &no_custom()).is_err());
+        assert!(Mask::parse("?d?", &no_custom()).is_err());
+        assert!(Mask::parse("?x", &no_custom()).is_err());
+        assert!(Mask::parse("?2", &no_custom()).is_err());
+    }
+}
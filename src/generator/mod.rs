@@ -1,3 +1,55 @@
+use std::any::Any;
+
+use crate::error::Result;
+use crate::rng::RandomSource;
+
+pub mod apikey;
 pub mod charset;
+pub mod derive;
+pub mod id;
+pub mod keyboard;
+pub mod keypair;
+pub mod mask;
+pub mod otp;
 pub mod passphrase;
+pub mod pronounceable;
 pub mod random;
+pub mod token;
+pub mod unicode;
+
+/// A fully configured generator of one kind of password or secret. Every
+/// subcommand and profile that prints one value per line goes through it.
+pub trait Generator: Any + Send + Sync {
+    /// Generates one value, drawing all randomness from `rng`.
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String>;
+
+    /// A one-line summary of what is generated.
+    fn describe(&self) -> String;
+
+    /// The kind of value, such as `password` or `passphrase`.
+    fn kind(&self) -> &'static str;
+
+    /// Number of characters each position is drawn from, for generators
+    /// with a single alphabet.
+    fn alphabet(&self) -> Option<usize> {
+        None
+    }
+
+    /// Entropy in bits of one value when the options fix the search space,
+    /// or `None` to fall back to the zxcvbn estimate of each output.
+    fn entropy(&self) -> Option<f64>;
+
+    /// Returns false when [`entropy`](Self::entropy) is only a lower bound.
+    fn entropy_is_exact(&self) -> bool {
+        true
+    }
+
+    /// Returns false when values depend on more than the random source, such
+    /// as the current time, so that `pw derive` cannot reproduce them.
+    fn is_deterministic(&self) -> bool {
+        true
+    }
+
+    /// The options as `(name, value)` pairs.
+    fn parameters(&self) -> Vec<(&'static str, String)>;
+}
@@ -0,0 +1,363 @@
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::Zeroizing;
+
+use super::token::{BASE32, Encoding};
+use crate::error::{Error, Result};
+use crate::escape::percent_encode;
+use crate::rng::RandomSource;
+
+/// The HMAC hash of an OTP. SHA1 is what most authenticator apps support.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 3] = [Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512];
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.to_ascii_uppercase().replace('-', "");
+        Algorithm::ALL
+            .into_iter()
+            .find(|a| a.to_string() == name)
+            .ok_or_else(|| {
+                Error::InvalidOtp(format!(
+                    "unknown algorithm {s} (expected sha1, sha256 or sha512)"
+                ))
+            })
+    }
+}
+
+/// Time-based (RFC 6238) or counter-based (RFC 4226) codes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OtpKind {
+    Totp { period: u64 },
+    Hotp { counter: u64 },
+}
+
+/// How codes are computed from a secret; shared by the URI and the codes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OtpParams {
+    pub kind: OtpKind,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+}
+
+impl Default for OtpParams {
+    fn default() -> Self {
+        Self {
+            kind: OtpKind::Totp { period: 30 },
+            algorithm: Algorithm::Sha1,
+            digits: 6,
+        }
+    }
+}
+
+impl OtpParams {
+    pub fn validate(&self) -> Result<()> {
+        if !(6..=8).contains(&self.digits) {
+            return Err(Error::InvalidOtp(format!(
+                "{} digits, expected 6 to 8",
+                self.digits
+            )));
+        }
+        if self.kind == (OtpKind::Totp { period: 0 }) {
+            return Err(Error::InvalidOtp(
+                "period must be at least 1 second".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The code for `key`: at `unix_seconds` for TOTP, at the counter for
+    /// HOTP.
+    pub fn code(&self, key: &[u8], unix_seconds: u64) -> String {
+        let counter = match self.kind {
+            OtpKind::Totp { period } => unix_seconds / period,
+            OtpKind::Hotp { counter } => counter,
+        };
+        hotp(key, counter, self.digits, self.algorithm)
+    }
+
+    /// Seconds until the TOTP code at `unix_seconds` changes.
+    pub fn remaining(&self, unix_seconds: u64) -> Option<u64> {
+        match self.kind {
+            OtpKind::Totp { period } => Some(period - unix_seconds % period),
+            OtpKind::Hotp { .. } => None,
+        }
+    }
+}
+
+/// RFC 4226 HOTP: HMAC of the big-endian counter, dynamically truncated to
+/// 31 bits, reduced to `digits` decimal digits and zero-padded.
+pub fn hotp(key: &[u8], counter: u64, digits: u32, algorithm: Algorithm) -> String {
+    let message = counter.to_be_bytes();
+    let mac = match algorithm {
+        Algorithm::Sha1 => hmac::<Hmac<Sha1>>(key, &message),
+        Algorithm::Sha256 => hmac::<Hmac<Sha256>>(key, &message),
+        Algorithm::Sha512 => hmac::<Hmac<Sha512>>(key, &message),
+    };
+    let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+    let binary =
+        u32::from_be_bytes(mac[offset..offset + 4].try_into().expect("4 bytes")) & 0x7fff_ffff;
+    let code = binary as u64 % 10u64.pow(digits);
+    format!("{code:0width$}", width = digits as usize)
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Draws a `bytes`-byte secret, in unpadded base32 as authenticator apps
+/// expect it.
+pub fn generate_secret<R: RandomSource + ?Sized>(
+    bytes: usize,
+    rng: &mut R,
+) -> Result<Zeroizing<String>> {
+    if bytes < 10 {
+        return Err(Error::InvalidOtp(
+            "secrets need at least 10 bytes (RFC 4226 section 4)".to_string(),
+        ));
+    }
+    let mut key = Zeroizing::new(vec![0u8; bytes]);
+    rng.fill(&mut key)?;
+    let mut secret = Zeroizing::new(Encoding::Base32.encode(&key));
+    let unpadded = secret.trim_end_matches('=').len();
+    secret.truncate(unpadded);
+    Ok(secret)
+}
+
+/// Decodes a base32 secret, ignoring case, spaces and padding.
+pub fn decode_secret(secret: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let mut key = Zeroizing::new(Vec::new());
+    let mut buffer = 0u32;
+    let mut buffered = 0;
+    for c in secret.chars().filter(|&c| c != ' ' && c != '=') {
+        let value = BASE32
+            .iter()
+            .position(|&b| b as char == c.to_ascii_uppercase())
+            .ok_or_else(|| Error::InvalidOtp(format!("secret is not base32: {c:?}")))?;
+        buffer = (buffer << 5) | value as u32;
+        buffered += 5;
+        if buffered >= 8 {
+            buffered -= 8;
+            key.push((buffer >> buffered) as u8);
+        }
+    }
+    if key.is_empty() {
+        return Err(Error::InvalidOtp("secret is empty".to_string()));
+    }
+    Ok(key)
+}
+
+/// The `otpauth://` URI of the Google Authenticator key URI format, as
+/// scanned from an enrollment QR code.
+pub fn uri(secret: &str, issuer: &str, account: &str, params: &OtpParams) -> String {
+    let label = if issuer.is_empty() {
+        percent_encode(account)
+    } else {
+        format!("{}:{}", percent_encode(issuer), percent_encode(account))
+    };
+    let (kind, extra) = match params.kind {
+        OtpKind::Totp { period } => ("totp", format!("period={period}")),
+        OtpKind::Hotp { counter } => ("hotp", format!("counter={counter}")),
+    };
+    let mut uri = format!("otpauth://{kind}/{label}?secret={secret}");
+    if !issuer.is_empty() {
+        uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+    }
+    uri.push_str(&format!(
+        "&algorithm={}&digits={}&{extra}",
+        params.algorithm, params.digits
+    ));
+    uri
+}
+
+/// Reads the secret and parameters back from an `otpauth://` URI; missing
+/// parameters take their defaults.
+pub fn parse_uri(uri: &str) -> Result<(Zeroizing<String>, OtpParams)> {
+    let invalid = |reason: &str| Error::InvalidOtp(format!("invalid otpauth URI: {reason}"));
+    let rest = uri
+        .strip_prefix("otpauth://")
+        .ok_or_else(|| invalid("does not start with otpauth://"))?;
+    let (kind, rest) = rest.split_once('/').ok_or_else(|| invalid("no type"))?;
+    let query = rest.split_once('?').map_or("", |(_, query)| query);
+
+    let mut secret = None;
+    let mut params = OtpParams::default();
+    let mut period = 30;
+    let mut counter = None;
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| invalid(&format!("{name}={value}")))
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "secret" => secret = Some(Zeroizing::new(value.to_string())),
+            "algorithm" => params.algorithm = value.parse()?,
+            "digits" => {
+                params.digits =
+                    u32::try_from(number()?).map_err(|_| invalid(&format!("{name}={value}")))?
+            }
+            "period" => period = number()?,
+            "counter" => counter = Some(number()?),
+            _ => {}
+        }
+    }
+    params.kind = match kind.to_ascii_lowercase().as_str() {
+        "totp" => OtpKind::Totp { period },
+        "hotp" => OtpKind::Hotp {
+            counter: counter.ok_or_else(|| invalid("hotp without counter"))?,
+        },
+        _ => return Err(invalid(&format!("unknown type {kind}"))),
+    };
+    let secret = secret.ok_or_else(|| invalid("no secret"))?;
+    params.validate()?;
+    Ok((secret, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED_SHA1: &[u8] = b"12345678901234567890";
+    const SEED_SHA256: &[u8] = b"12345678901234567890123456789012";
+    const SEED_SHA512: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    #[test]
+    fn rfc4226_vectors() {
+        // RFC 4226 appendix D
+        let codes = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in codes.iter().enumerate() {
+            assert_eq!(hotp(SEED_SHA1, counter as u64, 6, Algorithm::Sha1), *code);
+        }
+    }
+
+    #[test]
+    fn rfc6238_vectors() {
+        // RFC 6238 appendix B
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, sha1, sha256, sha512) in vectors {
+            for (algorithm, key, code) in [
+                (Algorithm::Sha1, SEED_SHA1, sha1),
+                (Algorithm::Sha256, SEED_SHA256, sha256),
+                (Algorithm::Sha512, SEED_SHA512, sha512),
+            ] {
+                let params = OtpParams {
+                    algorithm,
+                    digits: 8,
+                    ..Default::default()
+                };
+                assert_eq!(params.code(key, time), code, "{algorithm} at {time}");
+            }
+        }
+    }
+
+    #[test]
+    fn secret_round_trip() {
+        let secret = generate_secret(20, &mut rand::rng()).unwrap();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(decode_secret(&secret).unwrap().len(), 20);
+        // Unpadded when the byte count is not a multiple of 5
+        let secret = generate_secret(16, &mut rand::rng()).unwrap();
+        assert!(!secret.contains('='));
+        assert_eq!(decode_secret(&secret).unwrap().len(), 16);
+        assert_eq!(
+            decode_secret("gezd gnbv gy3t qojq").unwrap().as_slice(),
+            b"1234567890"
+        );
+        assert!(decode_secret("not base32!").is_err());
+        assert!(generate_secret(8, &mut rand::rng()).is_err());
+    }
+
+    #[test]
+    fn uri_round_trip() {
+        let params = OtpParams {
+            algorithm: Algorithm::Sha256,
+            digits: 8,
+            kind: OtpKind::Totp { period: 60 },
+        };
+        let uri = uri("JBSWY3DPEHPK3PXP", "Acme Co", "svc@acme.com", &params);
+        assert_eq!(
+            uri,
+            "otpauth://totp/Acme%20Co:svc%40acme.com?secret=JBSWY3DPEHPK3PXP\
+             &issuer=Acme%20Co&algorithm=SHA256&digits=8&period=60"
+        );
+        let (secret, parsed) = parse_uri(&uri).unwrap();
+        assert_eq!(secret.as_str(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(parsed, params);
+    }
+
+    #[test]
+    fn hotp_uri() {
+        let params = OtpParams {
+            kind: OtpKind::Hotp { counter: 7 },
+            ..Default::default()
+        };
+        let uri = uri("JBSWY3DPEHPK3PXP", "", "alice", &params);
+        assert_eq!(
+            uri,
+            "otpauth://hotp/alice?secret=JBSWY3DPEHPK3PXP&algorithm=SHA1&digits=6&counter=7"
+        );
+        assert_eq!(parse_uri(&uri).unwrap().1, params);
+        assert!(parse_uri("otpauth://hotp/alice?secret=A").is_err());
+        assert!(parse_uri("https://example.com").is_err());
+        // 2^32 + 6 must not wrap around to 6 digits
+        for digits in ["4294967302", "12", "5"] {
+            let uri = format!("otpauth://totp/alice?secret=A&digits={digits}");
+            assert!(matches!(parse_uri(&uri), Err(Error::InvalidOtp(_))));
+        }
+    }
+
+    #[test]
+    fn invalid_params() {
+        let params = OtpParams {
+            digits: 4,
+            ..Default::default()
+        };
+        assert!(params.validate().is_err());
+        let params = OtpParams {
+            kind: OtpKind::Totp { period: 0 },
+            ..Default::default()
+        };
+        assert!(params.validate().is_err());
+        assert!("md5".parse::<Algorithm>().is_err());
+        assert_eq!("sha-256".parse::<Algorithm>(), Ok(Algorithm::Sha256));
+    }
+}
@@ -1,11 +1,17 @@
-use rand::Rng;
-
+use super::Generator;
+use crate::entropy;
+use crate::error::{Error, Result};
+use crate::rng::{self, RandomSource};
 use crate::wordlist;
 
+/// Diceware passphrase drawn from the EFF large wordlist (7776 words).
 pub struct PassphraseOptions {
+    /// Number of words, at least 1.
     pub words: usize,
     pub separator: String,
+    /// Capitalize the first letter of each word.
     pub capitalize: bool,
+    /// Append a random digit to the passphrase.
     pub append_digit: bool,
 }
 
@@ -20,15 +26,51 @@ impl Default for PassphraseOptions {
     }
 }
 
-pub fn generate_passphrase(opts: &PassphraseOptions) -> String {
+impl Generator for PassphraseOptions {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        generate_passphrase(self, rng)
+    }
+
+    fn describe(&self) -> String {
+        format!("Passphrase with {} words", self.words)
+    }
+
+    fn kind(&self) -> &'static str {
+        "passphrase"
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        Some(entropy::calculate_passphrase_entropy(
+            wordlist::get_wordlist().len(),
+            self.words,
+            self.append_digit,
+        ))
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("words", self.words.to_string()),
+            ("separator", self.separator.clone()),
+            ("capitalize", self.capitalize.to_string()),
+            ("append_digit", self.append_digit.to_string()),
+        ]
+    }
+}
+
+pub fn generate_passphrase<R: RandomSource + ?Sized>(
+    opts: &PassphraseOptions,
+    rng: &mut R,
+) -> Result<String> {
+    if opts.words == 0 {
+        return Err(Error::InvalidWordCount);
+    }
     let wordlist = wordlist::get_wordlist();
-    let mut rng = rand::rng();
 
     let selected: Vec<String> = (0..opts.words)
         .map(|_| {
-            let idx = rng.random_range(0..wordlist.len());
+            let idx = rng::below(rng, wordlist.len())?;
             let word = wordlist[idx].to_string();
-            if opts.capitalize {
+            Ok(if opts.capitalize {
                 let mut chars = word.chars();
                 match chars.next() {
                     Some(c) => c.to_uppercase().to_string() + chars.as_str(),
@@ -36,18 +78,18 @@ pub fn generate_passphrase(opts: &PassphraseOptions) -> String {
                 }
             } else {
                 word
-            }
+            })
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     let mut result = selected.join(&opts.separator);
 
     if opts.append_digit {
-        let digit = rng.random_range(0..10u8);
+        let digit = rng::below(rng, 10)?;
         result.push_str(&digit.to_string());
     }
 
-    result
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -57,7 +99,7 @@ mod tests {
     #[test]
     fn default_passphrase_has_4_words() {
         let opts = PassphraseOptions::default();
-        let pp = generate_passphrase(&opts);
+        let pp = generate_passphrase(&opts, &mut rand::rng()).unwrap();
         let word_count = pp.split('-').count();
         assert_eq!(word_count, 4);
     }
@@ -68,7 +110,7 @@ mod tests {
             separator: ".".to_string(),
             ..Default::default()
         };
-        let pp = generate_passphrase(&opts);
+        let pp = generate_passphrase(&opts, &mut rand::rng()).unwrap();
         assert!(pp.contains('.'));
         assert!(!pp.contains('-'));
     }
@@ -79,7 +121,7 @@ mod tests {
             capitalize: true,
             ..Default::default()
         };
-        let pp = generate_passphrase(&opts);
+        let pp = generate_passphrase(&opts, &mut rand::rng()).unwrap();
         for word in pp.split('-') {
             assert!(word.chars().next().unwrap().is_uppercase());
         }
@@ -91,7 +133,19 @@ mod tests {
             append_digit: true,
             ..Default::default()
         };
-        let pp = generate_passphrase(&opts);
+        let pp = generate_passphrase(&opts, &mut rand::rng()).unwrap();
         assert!(pp.chars().last().unwrap().is_ascii_digit());
     }
+
+    #[test]
+    fn zero_words_is_an_error() {
+        let opts = PassphraseOptions {
+            words: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_passphrase(&opts, &mut rand::rng()),
+            Err(Error::InvalidWordCount)
+        );
+    }
 }
@@ -0,0 +1,201 @@
+use super::Generator;
+use super::charset::DIGITS;
+use crate::entropy;
+use crate::error::{Error, Result};
+use crate::rng::{self, RandomSource};
+
+/// Consonants with a single obvious spelling (no c, q, w, x or y).
+const CONSONANTS: &str = "bdfghjklmnprstvz";
+const VOWELS: &str = "aeiou";
+/// Symbols with a short, unambiguous spoken name.
+const SPOKEN_SYMBOLS: &str = "!@#$%&*+-=?";
+
+pub struct PronounceableOptions {
+    pub length: usize,
+    pub digits: usize,
+    pub symbols: usize,
+    pub capitalize: bool,
+}
+
+impl Default for PronounceableOptions {
+    fn default() -> Self {
+        Self {
+            length: 12,
+            digits: 0,
+            symbols: 0,
+            capitalize: false,
+        }
+    }
+}
+
+impl PronounceableOptions {
+    fn letters(&self) -> usize {
+        self.length - self.digits - self.symbols
+    }
+
+    /// Exact entropy in bits of the generated password.
+    ///
+    /// Letters alternate consonant/vowel starting with a consonant, and the
+    /// digit and symbol positions are chosen uniformly. Since digits and
+    /// symbols never overlap the letter sets, each choice yields a distinct
+    /// password, so the bits simply add up. Fails like [`validate`] when the
+    /// digits and symbols do not fit in the length.
+    ///
+    /// [`validate`]: PronounceableOptions::validate
+    pub fn entropy(&self) -> Result<f64> {
+        self.validate()?;
+        let letters = self.letters();
+        let consonants = letters.div_ceil(2);
+        let vowels = letters / 2;
+        Ok(entropy::calculate_entropy(CONSONANTS.len(), consonants)
+            + entropy::calculate_entropy(VOWELS.len(), vowels)
+            + entropy::log2_binomial(self.length, self.digits)
+            + entropy::log2_binomial(self.length - self.digits, self.symbols)
+            + entropy::calculate_entropy(DIGITS.len(), self.digits)
+            + entropy::calculate_entropy(SPOKEN_SYMBOLS.len(), self.symbols))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.length == 0 {
+            return Err(Error::InvalidLength);
+        }
+        let required = self.digits + self.symbols;
+        if required > self.length {
+            return Err(Error::MinimumsExceedLength {
+                required,
+                length: self.length,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Generator for PronounceableOptions {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        generate_pronounceable(self, rng)
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{}-char pronounceable, {} digits, {} symbols",
+            self.length, self.digits, self.symbols
+        )
+    }
+
+    fn kind(&self) -> &'static str {
+        "pronounceable"
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        PronounceableOptions::entropy(self).ok()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("length", self.length.to_string()),
+            ("digits", self.digits.to_string()),
+            ("symbols", self.symbols.to_string()),
+            ("capitalize", self.capitalize.to_string()),
+        ]
+    }
+}
+
+pub fn generate_pronounceable<R: RandomSource + ?Sized>(
+    opts: &PronounceableOptions,
+    rng: &mut R,
+) -> Result<String> {
+    opts.validate()?;
+    let consonants: Vec<char> = CONSONANTS.chars().collect();
+    let vowels: Vec<char> = VOWELS.chars().collect();
+    let digits: Vec<char> = DIGITS.chars().collect();
+    let symbols: Vec<char> = SPOKEN_SYMBOLS.chars().collect();
+
+    let mut letters: Vec<char> = (0..opts.letters())
+        .map(|i| {
+            let set = if i % 2 == 0 { &consonants } else { &vowels };
+            let c = set[rng::below(rng, set.len())?];
+            Ok(if i == 0 && opts.capitalize {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            })
+        })
+        .collect::<Result<_>>()?;
+    letters.reverse();
+
+    // Pick the digit positions, then the symbol positions among the rest.
+    let mut slots: Vec<Option<char>> = vec![None; opts.length];
+    for pos in rng::sample_indices(rng, opts.length, opts.digits)? {
+        slots[pos] = Some(digits[rng::below(rng, digits.len())?]);
+    }
+    let free: Vec<usize> = (0..opts.length).filter(|&i| slots[i].is_none()).collect();
+    for pos in rng::sample_indices(rng, free.len(), opts.symbols)? {
+        slots[free[pos]] = Some(symbols[rng::below(rng, symbols.len())?]);
+    }
+
+    Ok(slots
+        .into_iter()
+        .map(|slot| slot.unwrap_or_else(|| letters.pop().unwrap()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_alternates_consonant_vowel() {
+        let pw =
+            generate_pronounceable(&PronounceableOptions::default(), &mut rand::rng()).unwrap();
+        assert_eq!(pw.len(), 12);
+        for (i, c) in pw.chars().enumerate() {
+            let set = if i % 2 == 0 { CONSONANTS } else { VOWELS };
+            assert!(set.contains(c), "unexpected {c} at {i} in {pw}");
+        }
+    }
+
+    #[test]
+    fn sprinkles_digits_and_symbols() {
+        let opts = PronounceableOptions {
+            digits: 2,
+            symbols: 1,
+            capitalize: true,
+            ..Default::default()
+        };
+        let pw = generate_pronounceable(&opts, &mut rand::rng()).unwrap();
+        assert_eq!(pw.len(), 12);
+        assert_eq!(pw.chars().filter(|c| c.is_ascii_digit()).count(), 2);
+        assert_eq!(
+            pw.chars().filter(|c| SPOKEN_SYMBOLS.contains(*c)).count(),
+            1
+        );
+        let letters: String = pw.chars().filter(|c| c.is_alphabetic()).collect();
+        assert_eq!(letters.len(), 9);
+        assert!(letters.chars().next().unwrap().is_ascii_uppercase());
+    }
+
+    #[test]
+    fn entropy_is_exact() {
+        // 4 letters (CVCV) and 1 digit in one of 5 positions
+        let opts = PronounceableOptions {
+            length: 5,
+            digits: 1,
+            ..Default::default()
+        };
+        let expected = (16.0f64 * 5.0 * 16.0 * 5.0 * 5.0 * 10.0).log2();
+        assert!((opts.entropy().unwrap() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn too_many_extras_is_an_error() {
+        let opts = PronounceableOptions {
+            length: 4,
+            digits: 3,
+            symbols: 2,
+            ..Default::default()
+        };
+        assert!(generate_pronounceable(&opts, &mut rand::rng()).is_err());
+        assert!(opts.entropy().is_err());
+        assert_eq!(Generator::entropy(&opts), None);
+    }
+}
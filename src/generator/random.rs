@@ -1,77 +1,758 @@
-use rand::Rng;
-
-pub fn generate_password(charset: &[char], length: usize) -> String {
-    let mut rng = rand::rng();
-    (0..length)
-        .map(|_| {
-            let idx = rng.random_range(0..charset.len());
-            charset[idx]
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use unicode_normalization::UnicodeNormalization;
+
+use super::Generator;
+use super::charset::{Charset, CharsetBuilder, MIN_ACCEPTANCE_LOG2};
+use super::keyboard::Layout;
+use crate::entropy;
+use crate::error::{Error, Result};
+use crate::rng::{self, RandomSource};
+
+/// Upper bound on rejection sampling attempts. Configurations that pass
+/// [`PasswordConstraints::check`] need far fewer; hitting it means the
+/// requirements were never checked and are too restrictive.
+const MAX_ATTEMPTS: usize = 1 << 28;
+
+/// Limits of the exact count of passwords without forbidden runs: states
+/// per position, and states times positions. Larger counts would take
+/// minutes and gigabytes, and fall back to a lower bound.
+const MAX_RUN_STATES: usize = 1 << 20;
+const MAX_RUN_WORK: usize = 1 << 26;
+
+/// Optional restrictions on the shape of random passwords.
+#[derive(Debug, Clone, Default)]
+pub struct PasswordConstraints {
+    /// Forbid runs of this many identical characters (e.g. 3 forbids `aaa`).
+    pub no_repeat: Option<usize>,
+    /// Forbid ascending runs of this many consecutive letters or digits
+    /// (e.g. 3 forbids `abc` and `123`).
+    pub no_sequential: Option<usize>,
+    /// Use each character at most once.
+    pub unique_chars: bool,
+}
+
+impl PasswordConstraints {
+    fn is_empty(&self) -> bool {
+        self.no_repeat.is_none() && self.no_sequential.is_none() && !self.unique_chars
+    }
+
+    /// Cheap structural checks, done before every generation.
+    fn validate(&self, charset: &Charset, length: usize) -> Result<()> {
+        if length == 0 {
+            return Err(Error::InvalidLength);
+        }
+        if let Some(n) = self.no_repeat
+            && n < 2
+        {
+            return Err(Error::InvalidConstraint(
+                "--no-repeat must be at least 2".to_string(),
+            ));
+        }
+        if let Some(n) = self.no_sequential
+            && n < 2
+        {
+            return Err(Error::InvalidConstraint(
+                "--no-sequential must be at least 2".to_string(),
+            ));
+        }
+        if self.unique_chars {
+            if self.no_sequential.is_some() {
+                return Err(Error::InvalidConstraint(
+                    "--unique-chars cannot be combined with --no-sequential".to_string(),
+                ));
+            }
+            if length > charset.chars().len() {
+                return Err(Error::InvalidConstraint(format!(
+                    "--unique-chars needs {length} distinct characters, but the alphabet has {}",
+                    charset.chars().len()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that passwords of `length` satisfying both these constraints and
+    /// the charset minimums exist and can be sampled in reasonable time.
+    pub fn check(&self, charset: &Charset, length: usize) -> Result<()> {
+        self.validate(charset, length)?;
+        charset.check(length)?;
+        if self.is_empty() {
+            return Ok(());
+        }
+        if self.unique_chars {
+            let unconstrained = entropy::calculate_distinct_constrained_entropy(
+                &[charset.chars().len()],
+                &[0],
+                length,
+            );
+            if self.entropy(charset, length) - unconstrained < MIN_ACCEPTANCE_LOG2 {
+                return Err(Error::ConstraintsTooRestrictive { length });
+            }
+            return Ok(());
+        }
+        // The exact count is only needed when the bound leaves doubt
+        if self.acceptance_bound(charset, length) >= MIN_ACCEPTANCE_LOG2 {
+            return Ok(());
+        }
+        let unconstrained = entropy::calculate_entropy(charset.chars().len(), length);
+        match run_constrained_entropy(charset, length, self.no_repeat, self.no_sequential) {
+            Some(bits) if bits - unconstrained >= MIN_ACCEPTANCE_LOG2 => Ok(()),
+            _ => Err(Error::ConstraintsTooRestrictive { length }),
+        }
+    }
+
+    /// Lower bound on the log2 probability that a uniform draw meets the
+    /// minimums and has no forbidden run: the probability of meeting the
+    /// minimums, less that of a run starting at any position.
+    fn acceptance_bound(&self, charset: &Charset, length: usize) -> f64 {
+        let n = charset.chars().len();
+        let runs = |limit: Option<usize>| {
+            limit.map_or(0.0, |r| {
+                length.saturating_sub(r - 1) as f64 * (n as f64).powf(-((r - 1) as f64))
+            })
+        };
+        let minimums = (charset.entropy(length) - entropy::calculate_entropy(n, length)).exp2();
+        let p = minimums - runs(self.no_repeat) - runs(self.no_sequential);
+        if p > 0.0 { p.log2() } else { f64::NEG_INFINITY }
+    }
+
+    /// Returns false when [`entropy`](Self::entropy) is only a lower bound.
+    pub fn entropy_is_exact(&self, charset: &Charset, length: usize) -> bool {
+        self.unique_chars
+            || (self.no_repeat.is_none() && self.no_sequential.is_none())
+            || run_states(charset, length, self.no_repeat, self.no_sequential).is_some()
+    }
+
+    /// Entropy in bits of a uniform choice among the passwords of `length`
+    /// allowed by these constraints and the charset minimums. It is exact,
+    /// unless counting passwords without forbidden runs would take too long:
+    /// it is then a lower bound, from the same bound as [`check`].
+    ///
+    /// [`check`]: Self::check
+    pub fn entropy(&self, charset: &Charset, length: usize) -> f64 {
+        if self.unique_chars {
+            let sizes: Vec<usize> = charset.classes().iter().map(|c| c.chars.len()).collect();
+            let minimums: Vec<usize> = charset.classes().iter().map(|c| c.min).collect();
+            if sizes.is_empty() {
+                return entropy::calculate_distinct_constrained_entropy(
+                    &[charset.chars().len()],
+                    &[0],
+                    length,
+                );
+            }
+            return entropy::calculate_distinct_constrained_entropy(&sizes, &minimums, length);
+        }
+        if self.no_repeat.is_none() && self.no_sequential.is_none() {
+            return charset.entropy(length);
+        }
+        run_constrained_entropy(charset, length, self.no_repeat, self.no_sequential).unwrap_or_else(
+            || {
+                entropy::calculate_entropy(charset.chars().len(), length)
+                    + self.acceptance_bound(charset, length)
+            },
+        )
+    }
+
+    /// Returns true if `password` has no forbidden repeated or sequential run.
+    fn allows(&self, password: &[char]) -> bool {
+        let runs = longest_runs(password);
+        !(self.no_repeat.is_some_and(|n| runs.repeat >= n)
+            || self.no_sequential.is_some_and(|n| runs.sequential >= n))
+    }
+}
+
+/// Longest runs of identical and of ascending characters in a password.
+pub struct Runs {
+    pub repeat: usize,
+    pub sequential: usize,
+}
+
+pub fn longest_runs(password: &[char]) -> Runs {
+    let mut longest = Runs {
+        repeat: password.len().min(1),
+        sequential: password.len().min(1),
+    };
+    let mut repeat = 1;
+    let mut sequential = 1;
+    for pair in password.windows(2) {
+        repeat = if pair[1] == pair[0] { repeat + 1 } else { 1 };
+        sequential = if successor(pair[0]) == Some(pair[1]) {
+            sequential + 1
+        } else {
+            1
+        };
+        longest.repeat = longest.repeat.max(repeat);
+        longest.sequential = longest.sequential.max(sequential);
+    }
+    longest
+}
+
+/// The next character in an ascending sequence, for letters and digits only:
+/// `a` -> `b`, `8` -> `9`, but `9` -> `:` does not count.
+fn successor(c: char) -> Option<char> {
+    let next = char::from_u32(c as u32 + 1)?;
+    (c.is_alphanumeric() && next.is_alphanumeric()).then_some(next)
+}
+
+/// Number of states of [`run_constrained_entropy`], or `None` above the
+/// limits of an exact count.
+fn run_states(
+    charset: &Charset,
+    length: usize,
+    no_repeat: Option<usize>,
+    no_sequential: Option<usize>,
+) -> Option<usize> {
+    let states = charset
+        .classes()
+        .iter()
+        .try_fold(charset.chars().len(), |states, class| {
+            states.checked_mul(class.min + 1)
+        })?
+        .checked_mul(no_repeat.map_or(1, |r| r - 1))?
+        .checked_mul(no_sequential.map_or(1, |s| s - 1))?;
+    (states <= MAX_RUN_STATES && states.checked_mul(length)? <= MAX_RUN_WORK).then_some(states)
+}
+
+/// Counts passwords with no forbidden runs and all minimums met, or returns
+/// `None` when that would exceed the limits of [`run_states`].
+///
+/// Walks the password position by position, tracking for each last character
+/// the current repeat run, the current ascending run and the minimums still
+/// missing. Counts are renormalized at every step and the scale is kept in
+/// log2 space.
+fn run_constrained_entropy(
+    charset: &Charset,
+    length: usize,
+    no_repeat: Option<usize>,
+    no_sequential: Option<usize>,
+) -> Option<f64> {
+    let states = run_states(charset, length, no_repeat, no_sequential)?;
+    let chars = charset.chars();
+    let n = chars.len();
+    // Run lengths beyond what is forbidden never matter, so untracked
+    // constraints collapse to a single state.
+    let max_repeat = no_repeat.map_or(1, |r| r - 1);
+    let max_sequential = no_sequential.map_or(1, |s| s - 1);
+
+    let classes = charset.classes();
+    let required: Vec<usize> = classes.iter().map(|c| c.min).collect();
+    let deficit_states: usize = required.iter().map(|m| m + 1).product();
+    let class_of: Vec<Option<usize>> = chars
+        .iter()
+        .map(|c| {
+            classes
+                .iter()
+                .position(|class| class.min > 0 && class.chars.contains(c))
+        })
+        .collect();
+    // Deficits are encoded in mixed radix, one digit per class.
+    let strides: Vec<usize> = required
+        .iter()
+        .scan(1, |stride, m| {
+            let current = *stride;
+            *stride *= m + 1;
+            Some(current)
+        })
+        .collect();
+    let after = |deficit: usize, char_idx: usize| match class_of[char_idx] {
+        Some(class) if !(deficit / strides[class]).is_multiple_of(required[class] + 1) => {
+            deficit - strides[class]
+        }
+        _ => deficit,
+    };
+    let position: HashMap<char, usize> = chars.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+    let successor_idx: Vec<Option<usize>> = chars
+        .iter()
+        .map(|&c| successor(c).and_then(|next| position.get(&next).copied()))
+        .collect();
+    let mut predecessor: Vec<Option<usize>> = vec![None; n];
+    for (c, succ) in successor_idx.iter().enumerate() {
+        if let Some(succ) = succ {
+            predecessor[*succ] = Some(c);
+        }
+    }
+
+    let index = |c: usize, r: usize, s: usize, d: usize| {
+        ((c * max_repeat + (r - 1)) * max_sequential + (s - 1)) * deficit_states + d
+    };
+    let full_deficit: usize = required.iter().zip(&strides).map(|(m, s)| m * s).sum();
+
+    let mut counts = vec![0.0f64; states];
+    for c in 0..n {
+        counts[index(c, 1, 1, after(full_deficit, c))] += 1.0;
+    }
+    let mut log_scale = 0.0;
+
+    for _ in 1..length {
+        let total: f64 = counts.iter().sum();
+        if total == 0.0 {
+            return Some(f64::NEG_INFINITY);
+        }
+        log_scale += total.log2();
+        counts.iter_mut().for_each(|x| *x /= total);
+
+        // by_deficit[d]: all states with deficit d; by_last[c][d]: those ending in c
+        let mut by_deficit = vec![0.0; deficit_states];
+        let mut by_last = vec![0.0; n * deficit_states];
+        let mut next = vec![0.0; counts.len()];
+        for c in 0..n {
+            for r in 1..=max_repeat {
+                for s in 1..=max_sequential {
+                    for d in 0..deficit_states {
+                        let x = counts[index(c, r, s, d)];
+                        if x == 0.0 {
+                            continue;
+                        }
+                        by_deficit[d] += x;
+                        by_last[c * deficit_states + d] += x;
+                        // Same character again
+                        if no_repeat.is_none() {
+                            next[index(c, 1, 1, after(d, c))] += x;
+                        } else if r < max_repeat {
+                            next[index(c, r + 1, 1, after(d, c))] += x;
+                        }
+                        // Next character in sequence
+                        if let Some(succ) = successor_idx[c] {
+                            if no_sequential.is_none() {
+                                next[index(succ, 1, 1, after(d, succ))] += x;
+                            } else if s < max_sequential {
+                                next[index(succ, 1, s + 1, after(d, succ))] += x;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // Any other character starts fresh runs
+        for c in 0..n {
+            for d in 0..deficit_states {
+                let mut x = by_deficit[d] - by_last[c * deficit_states + d];
+                if let Some(pred) = predecessor[c] {
+                    x -= by_last[pred * deficit_states + d];
+                }
+                if x > 0.0 {
+                    next[index(c, 1, 1, after(d, c))] += x;
+                }
+            }
+        }
+        counts = next;
+    }
+
+    let valid: f64 = (0..n)
+        .flat_map(|c| {
+            (1..=max_repeat).flat_map(move |r| (1..=max_sequential).map(move |s| (c, r, s)))
         })
-        .collect()
+        .map(|(c, r, s)| counts[index(c, r, s, 0)])
+        .sum();
+    Some(log_scale + valid.log2())
+}
+
+/// Generates a password by drawing uniformly from the charset.
+///
+/// Class minimums are enforced by rejection: candidates that miss a minimum
+/// are discarded and redrawn, so every valid password stays equally likely.
+///
+/// Every charset character is a standalone grapheme, so `length` counts
+/// user-perceived characters. The output is NFC-normalized.
+pub fn generate_password<R: RandomSource + ?Sized>(
+    charset: &Charset,
+    length: usize,
+    rng: &mut R,
+) -> Result<String> {
+    generate_constrained_password(charset, length, &PasswordConstraints::default(), rng)
+}
+
+/// Like [`generate_password`], with the extra `constraints` also enforced by
+/// rejection. With `unique_chars`, candidates are drawn without replacement,
+/// which is uniform over all passwords without repeated characters.
+pub fn generate_constrained_password<R: RandomSource + ?Sized>(
+    charset: &Charset,
+    length: usize,
+    constraints: &PasswordConstraints,
+    rng: &mut R,
+) -> Result<String> {
+    constraints.validate(charset, length)?;
+    let chars = charset.chars();
+    let mut candidate = Vec::with_capacity(length);
+    for _ in 0..MAX_ATTEMPTS {
+        candidate.clear();
+        if constraints.unique_chars {
+            candidate.extend(
+                rng::sample_indices(rng, chars.len(), length)?
+                    .into_iter()
+                    .map(|i| chars[i]),
+            );
+        } else {
+            for _ in 0..length {
+                candidate.push(chars[rng::below(rng, chars.len())?]);
+            }
+        }
+        if charset.satisfies(&candidate) && constraints.allows(&candidate) {
+            return Ok(candidate.iter().copied().nfc().collect());
+        }
+    }
+    Err(Error::ConstraintsTooRestrictive { length })
+}
+
+/// Random passwords of a fixed length over a charset, as made by `pw` and
+/// the character-set profiles.
+pub struct RandomPassword {
+    builder: CharsetBuilder,
+    charset: Charset,
+    length: usize,
+    constraints: PasswordConstraints,
+    /// Computed on first use: counting with runs forbidden is not free.
+    entropy: OnceLock<f64>,
+}
+
+impl RandomPassword {
+    /// Builds the charset and checks that passwords of `length` meeting its
+    /// minimums and `constraints` can be sampled.
+    pub fn new(
+        builder: CharsetBuilder,
+        length: usize,
+        constraints: PasswordConstraints,
+    ) -> Result<Self> {
+        let charset = builder.clone().build()?;
+        constraints.check(&charset, length)?;
+        Ok(Self {
+            builder,
+            charset,
+            length,
+            constraints,
+            entropy: OnceLock::new(),
+        })
+    }
+
+    pub fn charset(&self) -> &Charset {
+        &self.charset
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The same generator with another length or symbol set, or only the
+    /// characters typed the same way on `layouts`.
+    pub fn customize(
+        &self,
+        length: Option<usize>,
+        symbols: Option<String>,
+        layouts: Vec<Layout>,
+    ) -> Result<Self> {
+        let mut builder = self.builder.clone();
+        if symbols.is_some() {
+            builder = builder.custom_symbols(symbols);
+        }
+        if !layouts.is_empty() {
+            builder = builder.keyboard_safe(layouts);
+        }
+        Self::new(
+            builder,
+            length.unwrap_or(self.length),
+            self.constraints.clone(),
+        )
+    }
 }
 
-pub fn generate_uuid() -> String {
-    let mut rng = rand::rng();
-    let mut bytes = [0u8; 16];
-    for b in &mut bytes {
-        *b = rng.random();
-    }
-    // Set version 4
-    bytes[6] = (bytes[6] & 0x0f) | 0x40;
-    // Set variant 1
-    bytes[8] = (bytes[8] & 0x3f) | 0x80;
-
-    format!(
-        "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
-        bytes[0],
-        bytes[1],
-        bytes[2],
-        bytes[3],
-        bytes[4],
-        bytes[5],
-        bytes[6],
-        bytes[7],
-        bytes[8],
-        bytes[9],
-        bytes[10],
-        bytes[11],
-        bytes[12],
-        bytes[13],
-        bytes[14],
-        bytes[15]
-    )
+impl Generator for RandomPassword {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        generate_constrained_password(&self.charset, self.length, &self.constraints, rng)
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} chars from {} characters",
+            self.length,
+            self.charset.chars().len()
+        )
+    }
+
+    fn kind(&self) -> &'static str {
+        "password"
+    }
+
+    fn alphabet(&self) -> Option<usize> {
+        Some(self.charset.chars().len())
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        Some(
+            *self
+                .entropy
+                .get_or_init(|| self.constraints.entropy(&self.charset, self.length)),
+        )
+    }
+
+    fn entropy_is_exact(&self) -> bool {
+        self.constraints
+            .entropy_is_exact(&self.charset, self.length)
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        let mut parameters = vec![
+            ("length", self.length.to_string()),
+            ("charset", self.charset.chars().iter().collect()),
+        ];
+        for class in self.charset.classes().iter().filter(|c| c.min > 0) {
+            parameters.push(("min", format!("{} {}", class.min, class.name)));
+        }
+        if let Some(n) = self.constraints.no_repeat {
+            parameters.push(("no_repeat", n.to_string()));
+        }
+        if let Some(n) = self.constraints.no_sequential {
+            parameters.push(("no_sequential", n.to_string()));
+        }
+        if self.constraints.unique_chars {
+            parameters.push(("unique_chars", "true".to_string()));
+        }
+        parameters
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Error;
+    use crate::generator::charset::CharsetBuilder;
+    use crate::generator::unicode::UnicodeClass;
+    use unicode_segmentation::UnicodeSegmentation;
 
     #[test]
     fn password_has_correct_length() {
-        let charset: Vec<char> = "abc123".chars().collect();
-        let pw = generate_password(&charset, 20);
+        let charset = Charset::from("abc123".chars().collect::<Vec<char>>());
+        let pw = generate_password(&charset, 20, &mut rand::rng()).unwrap();
         assert_eq!(pw.len(), 20);
     }
 
     #[test]
     fn password_uses_only_charset() {
-        let charset: Vec<char> = "abc".chars().collect();
-        let pw = generate_password(&charset, 100);
-        assert!(pw.chars().all(|c| charset.contains(&c)));
+        let charset = Charset::from("abc".chars().collect::<Vec<char>>());
+        let pw = generate_password(&charset, 100, &mut rand::rng()).unwrap();
+        assert!(pw.chars().all(|c| charset.chars().contains(&c)));
+    }
+
+    #[test]
+    fn password_meets_minimums() {
+        let charset = CharsetBuilder::new()
+            .min_digits(3)
+            .min_symbols(2)
+            .build()
+            .unwrap();
+        for _ in 0..50 {
+            let pw = generate_password(&charset, 8, &mut rand::rng()).unwrap();
+            assert!(pw.chars().filter(|c| c.is_ascii_digit()).count() >= 3);
+            assert!(pw.chars().filter(|c| !c.is_alphanumeric()).count() >= 2);
+        }
+    }
+
+    #[test]
+    fn unicode_length_counts_graphemes() {
+        let charset = CharsetBuilder::new()
+            .uppercase(false)
+            .lowercase(false)
+            .digits(false)
+            .custom_symbols(Some("e\u{301}€".to_string()))
+            .unicode_class(UnicodeClass::Emoji)
+            .build()
+            .unwrap();
+        let pw = generate_password(&charset, 24, &mut rand::rng()).unwrap();
+        assert_eq!(pw.graphemes(true).count(), 24);
+        assert!(pw.nfc().eq(pw.chars()));
+    }
+
+    fn digits() -> Charset {
+        CharsetBuilder::new()
+            .uppercase(false)
+            .lowercase(false)
+            .symbols(false)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn constraints_reject_runs() {
+        let constraints = PasswordConstraints {
+            no_repeat: Some(3),
+            no_sequential: Some(3),
+            ..Default::default()
+        };
+        assert!(constraints.allows(&['a', 'a', 'b', 'd', '1', '2']));
+        assert!(!constraints.allows(&['x', 'a', 'a', 'a']));
+        assert!(!constraints.allows(&['x', '1', '2', '3']));
+        assert!(constraints.allows(&['8', '9', ':']));
+    }
+
+    #[test]
+    fn constrained_passwords_have_no_runs() {
+        let constraints = PasswordConstraints {
+            no_repeat: Some(2),
+            no_sequential: Some(2),
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            let pw: Vec<char> =
+                generate_constrained_password(&digits(), 12, &constraints, &mut rand::rng())
+                    .unwrap()
+                    .chars()
+                    .collect();
+            for pair in pw.windows(2) {
+                assert_ne!(pair[0], pair[1]);
+                assert_ne!(pair[0] as u32 + 1, pair[1] as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn unique_chars_never_repeat() {
+        let constraints = PasswordConstraints {
+            unique_chars: true,
+            ..Default::default()
+        };
+        let mut pw: Vec<char> =
+            generate_constrained_password(&digits(), 10, &constraints, &mut rand::rng())
+                .unwrap()
+                .chars()
+                .collect();
+        pw.sort();
+        assert_eq!(pw, "0123456789".chars().collect::<Vec<_>>());
+        assert!(
+            generate_constrained_password(&digits(), 11, &constraints, &mut rand::rng()).is_err()
+        );
+    }
+
+    #[test]
+    fn run_entropy_counts_exactly() {
+        // Two digits with no repeat: 10 * 9
+        let no_repeat = PasswordConstraints {
+            no_repeat: Some(2),
+            ..Default::default()
+        };
+        assert!((no_repeat.entropy(&digits(), 2) - 90f64.log2()).abs() < 1e-9);
+
+        // Two digits, no ascending pair: 100 minus 01, 12, ..., 89
+        let no_sequential = PasswordConstraints {
+            no_sequential: Some(2),
+            ..Default::default()
+        };
+        assert!((no_sequential.entropy(&digits(), 2) - 91f64.log2()).abs() < 1e-9);
+
+        // Three digits, neither aaa nor abc: 1000 - 10 - 8
+        let both = PasswordConstraints {
+            no_repeat: Some(3),
+            no_sequential: Some(3),
+            ..Default::default()
+        };
+        assert!((both.entropy(&digits(), 3) - 982f64.log2()).abs() < 1e-9);
+
+        let unique = PasswordConstraints {
+            unique_chars: true,
+            ..Default::default()
+        };
+        assert!((unique.entropy(&digits(), 3) - 720f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn run_entropy_with_minimums() {
+        // Length 2 over a-c and 0-1 with at least one digit and no repeat:
+        // strings with a digit (25 - 9 = 16) minus 00 and 11
+        let charset = CharsetBuilder::new()
+            .uppercase(false)
+            .symbols(false)
+            .exclude(Some("defghijklmnopqrstuvwxyz23456789".to_string()))
+            .min_digits(1)
+            .build()
+            .unwrap();
+        let constraints = PasswordConstraints {
+            no_repeat: Some(2),
+            ..Default::default()
+        };
+        assert!((constraints.entropy(&charset, 2) - 14f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn run_entropy_matches_brute_force() {
+        let charset = CharsetBuilder::new()
+            .uppercase(false)
+            .symbols(false)
+            .exclude(Some("efghijklmnopqrstuvwxyz3456789".to_string()))
+            .min_lowercase(1)
+            .min_digits(2)
+            .build()
+            .unwrap();
+        let constraints = PasswordConstraints {
+            no_repeat: Some(2),
+            no_sequential: Some(3),
+            ..Default::default()
+        };
+        let chars = charset.chars();
+        let length = 5;
+        let mut valid = 0u32;
+        for mut i in 0..chars.len().pow(length as u32) {
+            let candidate: Vec<char> = (0..length)
+                .map(|_| {
+                    let c = chars[i % chars.len()];
+                    i /= chars.len();
+                    c
+                })
+                .collect();
+            if charset.satisfies(&candidate) && constraints.allows(&candidate) {
+                valid += 1;
+            }
+        }
+        let bits = constraints.entropy(&charset, length);
+        assert!((bits - f64::from(valid).log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn large_run_counts_fall_back_to_a_bound() {
+        let start = std::time::Instant::now();
+        let charset = CharsetBuilder::new()
+            .min_uppercase(20)
+            .min_lowercase(20)
+            .min_digits(20)
+            .min_symbols(20)
+            .build()
+            .unwrap();
+        let no_repeat = PasswordConstraints {
+            no_repeat: Some(3),
+            ..Default::default()
+        };
+        assert!(no_repeat.check(&charset, 128).is_ok());
+        assert!(!no_repeat.entropy_is_exact(&charset, 128));
+        let bound = no_repeat.entropy(&charset, 128);
+        assert!(bound > 0.0 && bound <= charset.entropy(128));
+
+        let long_runs = PasswordConstraints {
+            no_repeat: Some(500),
+            no_sequential: Some(500),
+            ..Default::default()
+        };
+        let charset = CharsetBuilder::new().build().unwrap();
+        assert!(long_runs.check(&charset, 1000).is_ok());
+        assert!(!long_runs.entropy_is_exact(&charset, 1000));
+        assert!((long_runs.entropy(&charset, 1000) - charset.entropy(1000)).abs() < 1e-6);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        // Where both exist, the bound stays below the exact count
+        let both = PasswordConstraints {
+            no_repeat: Some(3),
+            no_sequential: Some(3),
+            ..Default::default()
+        };
+        assert!(both.entropy_is_exact(&digits(), 8));
+        let bound = entropy::calculate_entropy(10, 8) + both.acceptance_bound(&digits(), 8);
+        assert!(bound <= both.entropy(&digits(), 8));
     }
 
     #[test]
-    fn uuid_format() {
-        let uuid = generate_uuid();
-        assert_eq!(uuid.len(), 36);
-        let parts: Vec<&str> = uuid.split('-').collect();
-        assert_eq!(parts.len(), 5);
-        assert_eq!(parts[0].len(), 8);
-        assert_eq!(parts[1].len(), 4);
-        assert_eq!(parts[2].len(), 4);
-        assert_eq!(parts[3].len(), 4);
-        assert_eq!(parts[4].len(), 12);
-        // Version 4
-        assert!(parts[2].starts_with('4'));
+    fn zero_length_is_an_error() {
+        let charset = Charset::from(vec!['a']);
+        assert_eq!(
+            generate_password(&charset, 0, &mut rand::rng()),
+            Err(Error::InvalidLength)
+        );
     }
 }
@@ -0,0 +1,327 @@
+use std::fmt;
+use std::str::FromStr;
+
+use zeroize::Zeroizing;
+
+use super::Generator;
+use crate::error::{Error, Result};
+use crate::rng::RandomSource;
+
+const HEX_LOWER: &[u8] = b"0123456789abcdef";
+const HEX_UPPER: &[u8] = b"0123456789ABCDEF";
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+pub const BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+pub const BASE32_CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// Digits, then uppercase, then lowercase, as in KSUIDs and GitHub tokens.
+pub const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const Z85: &[u8] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+const PROQUINT_CONSONANTS: &[u8] = b"bdfghjklmnprstvz";
+const PROQUINT_VOWELS: &[u8] = b"aiou";
+
+/// How the random bytes of a token are turned into text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    HexLower,
+    HexUpper,
+    /// RFC 4648 base64, padded with `=`.
+    Base64,
+    /// RFC 4648 base64 without padding.
+    Base64Unpadded,
+    /// RFC 4648 URL-safe base64 (`-` and `_`), without padding.
+    Base64Url,
+    /// RFC 4648 base32, padded with `=`.
+    Base32,
+    /// Crockford's base32: no padding, no I, L, O or U.
+    Crockford,
+    /// ZeroMQ Z85; the byte count must be a multiple of 4.
+    Z85,
+    /// Pronounceable quintuplets, one per 16 bits, joined by `-`; the byte
+    /// count must be even.
+    Proquint,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 9] = [
+        Encoding::HexLower,
+        Encoding::HexUpper,
+        Encoding::Base64,
+        Encoding::Base64Unpadded,
+        Encoding::Base64Url,
+        Encoding::Base32,
+        Encoding::Crockford,
+        Encoding::Z85,
+        Encoding::Proquint,
+    ];
+
+    /// Number of distinct characters in encoded tokens, padding aside.
+    pub fn alphabet(self) -> Option<usize> {
+        match self {
+            Encoding::HexLower | Encoding::HexUpper => Some(16),
+            Encoding::Base64 | Encoding::Base64Unpadded | Encoding::Base64Url => Some(64),
+            Encoding::Base32 | Encoding::Crockford => Some(32),
+            Encoding::Z85 => Some(85),
+            Encoding::Proquint => None,
+        }
+    }
+
+    /// Checks that `bytes` random bytes can be encoded.
+    pub fn check(self, bytes: usize) -> Result<()> {
+        if bytes == 0 {
+            return Err(Error::InvalidLength);
+        }
+        let multiple = match self {
+            Encoding::Z85 => 4,
+            Encoding::Proquint => 2,
+            _ => 1,
+        };
+        if !bytes.is_multiple_of(multiple) {
+            return Err(Error::InvalidToken(format!(
+                "{self} needs a multiple of {multiple} bytes, got {bytes}"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::HexLower => encode_bits(bytes, HEX_LOWER, 4, false),
+            Encoding::HexUpper => encode_bits(bytes, HEX_UPPER, 4, false),
+            Encoding::Base64 => encode_bits(bytes, BASE64, 6, true),
+            Encoding::Base64Unpadded => encode_bits(bytes, BASE64, 6, false),
+            Encoding::Base64Url => encode_bits(bytes, BASE64_URL, 6, false),
+            Encoding::Base32 => encode_bits(bytes, BASE32, 5, true),
+            Encoding::Crockford => encode_bits(bytes, BASE32_CROCKFORD, 5, false),
+            Encoding::Z85 => encode_z85(bytes),
+            Encoding::Proquint => encode_proquint(bytes),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::HexLower => "hex",
+            Encoding::HexUpper => "HEX",
+            Encoding::Base64 => "base64",
+            Encoding::Base64Unpadded => "base64-nopad",
+            Encoding::Base64Url => "base64url",
+            Encoding::Base32 => "base32",
+            Encoding::Crockford => "crockford",
+            Encoding::Z85 => "z85",
+            Encoding::Proquint => "proquint",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "HEX" || s == "hex-upper" {
+            return Ok(Encoding::HexUpper);
+        }
+        Encoding::ALL
+            .into_iter()
+            .find(|e| e.to_string() == s.to_ascii_lowercase())
+            .ok_or_else(|| {
+                let names: Vec<String> = Encoding::ALL.iter().map(|e| e.to_string()).collect();
+                Error::InvalidToken(format!(
+                    "unknown encoding {s} (expected {})",
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Random bytes in a text encoding, as made by `pw token`.
+pub struct TokenOptions {
+    pub bytes: usize,
+    pub encoding: Encoding,
+}
+
+impl Default for TokenOptions {
+    fn default() -> Self {
+        Self {
+            bytes: 32,
+            encoding: Encoding::HexLower,
+        }
+    }
+}
+
+impl Generator for TokenOptions {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        generate_token(self.bytes, self.encoding, rng)
+    }
+
+    fn describe(&self) -> String {
+        format!("{} random bytes, {}", self.bytes, self.encoding)
+    }
+
+    fn kind(&self) -> &'static str {
+        "token"
+    }
+
+    fn alphabet(&self) -> Option<usize> {
+        self.encoding.alphabet()
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        Some(self.bytes as f64 * 8.0)
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("bytes", self.bytes.to_string()),
+            ("encoding", self.encoding.to_string()),
+        ]
+    }
+}
+
+/// Draws `bytes` random bytes and encodes them.
+pub fn generate_token<R: RandomSource + ?Sized>(
+    bytes: usize,
+    encoding: Encoding,
+    rng: &mut R,
+) -> Result<String> {
+    encoding.check(bytes)?;
+    let mut buf = Zeroizing::new(vec![0u8; bytes]);
+    rng.fill(&mut buf)?;
+    Ok(encoding.encode(&buf))
+}
+
+/// Splits `bytes` into big-endian groups of `bits`, the last one padded with
+/// zero bits, and pads the output with `=` to a whole RFC 4648 block if asked.
+fn encode_bits(bytes: &[u8], alphabet: &[u8], bits: u32, pad: bool) -> String {
+    let mut out = String::new();
+    let mut buffer = 0u32;
+    let mut buffered = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        buffered += 8;
+        while buffered >= bits {
+            buffered -= bits;
+            out.push(alphabet[((buffer >> buffered) & ((1 << bits) - 1)) as usize] as char);
+        }
+    }
+    if buffered > 0 {
+        out.push(alphabet[((buffer << (bits - buffered)) & ((1 << bits) - 1)) as usize] as char);
+    }
+    if pad {
+        // Blocks are 4 characters for base64 and 8 for base32
+        let block = if bits == 6 { 4 } else { 8 };
+        while !out.len().is_multiple_of(block) {
+            out.push('=');
+        }
+    }
+    out
+}
+
+fn encode_z85(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(4) {
+        let mut value = u32::from_be_bytes(chunk.try_into().expect("checked multiple of 4"));
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = Z85[(value % 85) as usize];
+            value /= 85;
+        }
+        out.extend(digits.iter().map(|&d| d as char));
+    }
+    out
+}
+
+fn encode_proquint(bytes: &[u8]) -> String {
+    let words: Vec<String> = bytes
+        .chunks(2)
+        .map(|pair| {
+            let word = u16::from_be_bytes(pair.try_into().expect("checked even length"));
+            let c = |shift: u16| PROQUINT_CONSONANTS[((word >> shift) & 0xf) as usize] as char;
+            let v = |shift: u16| PROQUINT_VOWELS[((word >> shift) & 0x3) as usize] as char;
+            [c(12), v(10), c(6), v(4), c(0)].iter().collect()
+        })
+        .collect();
+    words.join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc4648_vectors() {
+        let cases = [
+            ("", "", ""),
+            ("f", "Zg==", "MY======"),
+            ("fo", "Zm8=", "MZXQ===="),
+            ("foo", "Zm9v", "MZXW6==="),
+            ("foob", "Zm9vYg==", "MZXW6YQ="),
+            ("fooba", "Zm9vYmE=", "MZXW6YTB"),
+            ("foobar", "Zm9vYmFy", "MZXW6YTBOI======"),
+        ];
+        for (input, base64, base32) in cases {
+            assert_eq!(Encoding::Base64.encode(input.as_bytes()), base64);
+            assert_eq!(Encoding::Base32.encode(input.as_bytes()), base32);
+            assert_eq!(
+                Encoding::Base64Unpadded.encode(input.as_bytes()),
+                base64.trim_end_matches('=')
+            );
+        }
+        assert_eq!(Encoding::HexLower.encode(b"foobar"), "666f6f626172");
+        assert_eq!(Encoding::HexUpper.encode(&[0xab, 0x01]), "AB01");
+        assert_eq!(Encoding::Base64Url.encode(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn crockford_vector() {
+        // 0x0123456789 in 5-bit groups: 00000 00100 10001 10100 01010 11001 11100 01001
+        assert_eq!(
+            Encoding::Crockford.encode(&[0x01, 0x23, 0x45, 0x67, 0x89]),
+            "04HMASW9"
+        );
+    }
+
+    #[test]
+    fn z85_vector() {
+        // From the ZeroMQ RFC 32 specification
+        let bytes = [0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B];
+        assert_eq!(Encoding::Z85.encode(&bytes), "HelloWorld");
+    }
+
+    #[test]
+    fn proquint_vectors() {
+        // From the proquint specification: IPv4 addresses as two words
+        assert_eq!(Encoding::Proquint.encode(&[127, 0, 0, 1]), "lusab-babad");
+        assert_eq!(
+            Encoding::Proquint.encode(&[63, 84, 220, 193]),
+            "gutih-tugad"
+        );
+    }
+
+    #[test]
+    fn length_checks() {
+        assert!(Encoding::Z85.check(30).is_err());
+        assert!(Encoding::Z85.check(32).is_ok());
+        assert!(Encoding::Proquint.check(3).is_err());
+        assert_eq!(Encoding::Base64.check(0), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn parse_encodings() {
+        for encoding in Encoding::ALL {
+            assert_eq!(encoding.to_string().parse::<Encoding>(), Ok(encoding));
+        }
+        assert_eq!("hex-upper".parse::<Encoding>(), Ok(Encoding::HexUpper));
+        assert!("base58".parse::<Encoding>().is_err());
+    }
+
+    #[test]
+    fn token_length() {
+        let token = generate_token(32, Encoding::Base64Url, &mut rand::rng()).unwrap();
+        assert_eq!(token.len(), 43);
+        let token = generate_token(16, Encoding::Proquint, &mut rand::rng()).unwrap();
+        assert_eq!(token.split('-').count(), 8);
+    }
+}
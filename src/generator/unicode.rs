@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{Error, Result};
+
+/// A named block of non-ASCII characters that can be added to a charset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnicodeClass {
+    /// Letters from the Latin-1 Supplement (À..ÿ, without × and ÷).
+    Latin1,
+    /// Greek capital and small letters (Α..Ω, α..ω).
+    Greek,
+    /// Emoticons block (😀..🙏).
+    Emoji,
+    /// An inclusive code point range, `U+XXXX-U+YYYY`.
+    Range(u32, u32),
+}
+
+impl UnicodeClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnicodeClass::Latin1 => "Latin-1 letters",
+            UnicodeClass::Greek => "Greek letters",
+            UnicodeClass::Emoji => "emoji",
+            UnicodeClass::Range(..) => "Unicode range characters",
+        }
+    }
+
+    /// The standalone, NFC-normalized characters of the class, deduplicated.
+    pub fn chars(&self) -> Vec<char> {
+        let ranges: &[(u32, u32)] = match self {
+            UnicodeClass::Latin1 => &[(0xC0, 0xFF)],
+            UnicodeClass::Greek => &[(0x391, 0x3A9), (0x3B1, 0x3C9)],
+            UnicodeClass::Emoji => &[(0x1F600, 0x1F64F)],
+            UnicodeClass::Range(start, end) => &[(*start, *end)],
+        };
+
+        let letters_only = matches!(self, UnicodeClass::Latin1 | UnicodeClass::Greek);
+        let mut chars = Vec::new();
+        let mut seen = HashSet::new();
+        for &(start, end) in ranges {
+            for c in (start..=end).filter_map(char::from_u32) {
+                if let Some(c) = nfc_char(c)
+                    && is_standalone(c)
+                    && (!letters_only || c.is_alphabetic())
+                    && seen.insert(c)
+                {
+                    chars.push(c);
+                }
+            }
+        }
+        chars
+    }
+}
+
+impl fmt::Display for UnicodeClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnicodeClass::Latin1 => write!(f, "latin1"),
+            UnicodeClass::Greek => write!(f, "greek"),
+            UnicodeClass::Emoji => write!(f, "emoji"),
+            UnicodeClass::Range(start, end) => write!(f, "U+{start:04X}-U+{end:04X}"),
+        }
+    }
+}
+
+impl FromStr for UnicodeClass {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "latin1" => return Ok(UnicodeClass::Latin1),
+            "greek" => return Ok(UnicodeClass::Greek),
+            "emoji" => return Ok(UnicodeClass::Emoji),
+            _ => {}
+        }
+
+        let invalid = || Error::InvalidUnicodeClass(s.to_string());
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let code_point = |p: &str| {
+            let hex = p
+                .strip_prefix("U+")
+                .or_else(|| p.strip_prefix("u+"))
+                .ok_or_else(invalid)?;
+            u32::from_str_radix(hex, 16).map_err(|_| invalid())
+        };
+        let (start, end) = (code_point(start)?, code_point(end)?);
+        if start > end || end > char::MAX as u32 {
+            return Err(invalid());
+        }
+        Ok(UnicodeClass::Range(start, end))
+    }
+}
+
+/// Splits user-supplied text into an alphabet after NFC normalization.
+///
+/// Every user-perceived character must be a single standalone code point,
+/// so that one drawn `char` is always one character on screen.
+pub fn parse_set(text: &str) -> Result<Vec<char>> {
+    let normalized: String = text.nfc().collect();
+    normalized
+        .graphemes(true)
+        .map(|g| {
+            let mut chars = g.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if is_standalone(c) => Ok(c),
+                _ => Err(Error::UnsupportedCharacter(g.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// Returns true if `c` is visible and never merges with its neighbours into
+/// a single grapheme cluster (combining marks, joiners, regional indicators,
+/// Hangul jamo, emoji modifiers...).
+pub fn is_standalone(c: char) -> bool {
+    if c == ' ' || c.is_ascii_graphic() {
+        return true;
+    }
+    if !matches!(
+        c.general_category_group(),
+        GeneralCategoryGroup::Letter
+            | GeneralCategoryGroup::Number
+            | GeneralCategoryGroup::Punctuation
+            | GeneralCategoryGroup::Symbol
+    ) {
+        return false;
+    }
+    [format!("a{c}"), format!("{c}a"), format!("{c}{c}")]
+        .iter()
+        .all(|s| s.graphemes(true).count() == 2)
+}
+
+fn nfc_char(c: char) -> Option<char> {
+    let mut normalized = std::iter::once(c).nfc();
+    match (normalized.next(), normalized.next()) {
+        (Some(n), None) => Some(n),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_classes() {
+        let latin1 = UnicodeClass::Latin1.chars();
+        assert_eq!(latin1.len(), 62);
+        assert!(!latin1.contains(&'×') && !latin1.contains(&'÷'));
+        assert_eq!(UnicodeClass::Greek.chars().len(), 49);
+        assert_eq!(UnicodeClass::Emoji.chars().len(), 80);
+    }
+
+    #[test]
+    fn parse_classes() {
+        assert_eq!("greek".parse::<UnicodeClass>(), Ok(UnicodeClass::Greek));
+        assert_eq!(
+            "U+0100-U+017F".parse::<UnicodeClass>(),
+            Ok(UnicodeClass::Range(0x100, 0x17F))
+        );
+        assert!("U+017F-U+0100".parse::<UnicodeClass>().is_err());
+        assert!("cyrillic".parse::<UnicodeClass>().is_err());
+        assert!("U+0100-0x17F".parse::<UnicodeClass>().is_err());
+        assert_eq!(
+            UnicodeClass::Range(0x100, 0x17F).to_string(),
+            "U+0100-U+017F"
+        );
+    }
+
+    #[test]
+    fn ranges_drop_combining_marks() {
+        assert!(UnicodeClass::Range(0x300, 0x36F).chars().is_empty());
+        // Regional indicators pair up into flags
+        assert!(UnicodeClass::Range(0x1F1E6, 0x1F1FF).chars().is_empty());
+    }
+
+    #[test]
+    fn parse_set_normalizes() {
+        // e + combining acute composes to a single é
+        assert_eq!(parse_set("e\u{301}€"), Ok(vec!['é', '€']));
+        assert!(parse_set("\u{301}").is_err());
+        // Thumbs up with a skin tone modifier is two code points
+        assert!(parse_set("👍🏽").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+//! Password, passphrase and secret generation with the same rules, profiles
+//! and entropy estimates as the `pw` command-line tool.
+//!
+//! Generators take their options and a [`RandomSource`], which any `rand`
+//! generator implements; [`OsSource`] draws from the operating system.
+//!
+//! ```
+//! use pw::{CharsetBuilder, Generator, OsSource, PassphraseOptions};
+//!
+//! let charset = CharsetBuilder::new().no_ambiguous(true).build()?;
+//! let password = pw::generate_password(&charset, 20, &mut OsSource)?;
+//! assert_eq!(password.chars().count(), 20);
+//!
+//! let passphrase = pw::generate_passphrase(&PassphraseOptions::default(), &mut OsSource)?;
+//! let wifi = pw::find_profile("wifi").unwrap().generate(&mut OsSource)?;
+//! println!("{passphrase} {wifi} {:.0} bits", charset.entropy(20));
+//! # Ok::<(), pw::Error>(())
+//! ```
+//!
+//! Every generator implements [`Generator`], and profiles wrap one under a
+//! name; [`register_profile`] adds profiles that `find_profile` and
+//! `get_profiles` then return alongside the built-in ones.
+//!
+//! The library itself has no terminal dependencies. The `cli` and
+//! `clipboard` features, on by default, only build the `pw` binary with
+//! `clap`, `colored` and `arboard`; depend on this crate with
+//! `default-features = false` to leave them out.
+
+pub mod batch;
+pub mod entropy;
+pub mod error;
+pub mod escape;
+pub mod file;
+pub mod format;
+pub mod generator;
+pub mod policy;
+pub mod profile;
+pub mod qr;
+pub mod rng;
+pub mod wifi;
+mod wordlist;
+
+pub use entropy::{EntropyInfo, analyze_password};
+pub use error::{Error, Result};
+pub use generator::Generator;
+pub use generator::charset::{Charset, CharsetBuilder};
+pub use generator::passphrase::{PassphraseOptions, generate_passphrase};
+pub use generator::random::{
+    PasswordConstraints, generate_constrained_password, generate_password,
+};
+pub use policy::Policy;
+pub use profile::{Profile, Registry, find_profile, get_profiles, register_profile};
+pub use rng::{OsSource, RandomSource, RngKind};
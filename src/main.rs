@@ -1,105 +1,829 @@
 mod cli;
 mod clipboard;
-mod entropy;
-mod generator;
 mod output;
-mod profile;
-mod wordlist;
+
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use clap::Parser;
-use cli::{Cli, Command};
-use generator::charset::CharsetBuilder;
-use generator::passphrase::{self, PassphraseOptions};
-use generator::random;
+use cli::{ApikeyAction, Cli, Command, Emit, OtpAction};
+use pw::batch::{self, Batch};
+use pw::escape::Escape;
+use pw::file::{self, SecretFile};
+use pw::format::{Format, Record};
+use pw::generator::Generator;
+use pw::generator::apikey::{self, ApiKeyOptions, Checksum};
+use pw::generator::charset::{Charset, CharsetBuilder};
+use pw::generator::derive::{self, DeriveParams};
+use pw::generator::id::{self, IdFormat, IdOptions};
+use pw::generator::keypair::{self, KeyType};
+use pw::generator::mask::Mask;
+use pw::generator::otp::{self, OtpKind, OtpParams};
+use pw::generator::passphrase::PassphraseOptions;
+use pw::generator::pronounceable::PronounceableOptions;
+use pw::generator::random::{PasswordConstraints, RandomPassword};
+use pw::generator::token::TokenOptions;
+use pw::policy::Policy;
+use pw::profile::Profile;
+use pw::rng::{self, RandomSource, RngKind};
+use pw::{Error, Result, profile, qr, wifi};
+use zeroize::Zeroizing;
 
 fn main() {
     let cli = Cli::parse();
 
-    match cli.command {
-        Some(Command::Passphrase {
-            words,
-            separator,
-            capitalize,
-            append_digit,
-            count,
-            copy,
-            entropy: show_entropy,
+    if let Err(e) = run(cli) {
+        eprintln!("Error: {e}");
+        if let Error::UnknownProfile(_) = e {
+            eprintln!("Use 'pw profiles' to list available profiles.");
+        }
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run(mut cli: Cli) -> Result<()> {
+    // `pw id --format uuid7` names the ID type, as it did before --format
+    // became global; any other value is an output format
+    if let Some(Command::Id { id_format, .. }) = &mut cli.command
+        && cli.format.parse::<IdFormat>().is_ok()
+    {
+        *id_format = std::mem::replace(&mut cli.format, Format::Text.to_string());
+    }
+    let policy = cli.policy.as_deref().map(Policy::load).transpose()?;
+    let policy = policy.as_ref();
+    let seed = cli.seed.as_deref();
+    let source: RngKind = cli.rng.parse()?;
+    let source = &source;
+    let format: Format = cli.format.parse()?;
+    let escape: Option<Escape> = cli.escape.as_deref().map(str::parse).transpose()?;
+    let output_path = cli.output.as_deref();
+    let force = cli.force;
+    let threads = match cli.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let run = RunOptions {
+        count: cli.emit.count,
+        copy: cli.emit.copy,
+        show_entropy: cli.emit.entropy,
+        quiet: cli.emit.quiet,
+        policy,
+        seed,
+        source,
+        threads,
+        unique: cli.unique,
+        output: output_path,
+        force,
+        format,
+        escape,
+    };
+
+    let command = match cli.command {
+        Some(command) => command,
+        None => return run_generate(&run, &password_generator(&cli, policy)?),
+    };
+    if let Some((generator, emit)) = generator(&command)? {
+        return run_generate(&run.with(emit), generator.as_ref());
+    }
+    match command {
+        Command::Apikey {
+            action:
+                Some(ApikeyAction::Verify {
+                    key,
+                    prefix,
+                    checksum,
+                    quiet,
+                }),
+            ..
+        } => {
+            let checksum: Checksum = checksum.parse()?;
+            let key = match key {
+                Some(key) => key,
+                None => read_stdin_line(Error::InvalidApiKey)?,
+            };
+            apikey::verify_api_key(&key, prefix.as_deref(), checksum)?;
+            match format {
+                Format::Text => {
+                    output::print_verified(&format!("{checksum} checksum is valid"), quiet)
+                }
+                _ => output::print_records(
+                    format,
+                    &[Record::new()
+                        .with("valid", true)
+                        .with("checksum", checksum.to_string())
+                        .with("prefix", prefix)],
+                ),
+            }
+            Ok(())
+        }
+        Command::Id {
+            inspect: Some(id), ..
+        } => {
+            let info = id::inspect(&id)?;
+            match format {
+                Format::Text => output::print_id_info(&info),
+                _ => output::print_records(format, &[output::id_record(&info)]),
+            }
+            Ok(())
+        }
+        Command::Keypair {
+            key_type,
+            comment,
             quiet,
-        }) => {
-            let opts = PassphraseOptions {
-                words,
-                separator,
-                capitalize,
-                append_digit,
+        } => {
+            let key_type: KeyType = key_type.parse()?;
+            let mut rng = open_rng(seed, false, source)?;
+            let pair = keypair::generate_keypair(key_type, &comment, &mut *rng)?;
+            match output_path {
+                Some(path) => {
+                    let contents = match key_type {
+                        // age-keygen records the recipient next to the identity
+                        KeyType::Age => format!("# public key: {}\n{}", pair.public, *pair.private),
+                        _ => pair.private.to_string(),
+                    };
+                    keypair::write_private_key(path, &Zeroizing::new(contents), force)?;
+                    output::print_keypair(None, &pair.public, key_type, quiet, format);
+                }
+                None => output::print_keypair(
+                    Some(&pair.private),
+                    &pair.public,
+                    key_type,
+                    quiet,
+                    format,
+                ),
+            }
+            Ok(())
+        }
+        Command::Otp {
+            action:
+                OtpAction::New {
+                    issuer,
+                    account,
+                    digits,
+                    period,
+                    algorithm,
+                    bytes,
+                    hotp,
+                    counter,
+                    quiet,
+                },
+        } => {
+            let params = OtpParams {
+                kind: if hotp {
+                    OtpKind::Hotp { counter }
+                } else {
+                    OtpKind::Totp { period }
+                },
+                algorithm: algorithm.parse()?,
+                digits,
+            };
+            params.validate()?;
+            let mut rng = open_rng(seed, false, source)?;
+            let secret = otp::generate_secret(bytes, &mut *rng)?;
+            let uri = Zeroizing::new(otp::uri(&secret, &issuer, &account, &params));
+            let record = Record::new()
+                .with("secret", secret.as_str())
+                .with("uri", uri.as_str());
+            match (output_path, format) {
+                (Some(path), _) => {
+                    let contents = Zeroizing::new(match format {
+                        Format::Text => format!("{}\n{}\n", *secret, *uri),
+                        _ => format.records(&[record]),
+                    });
+                    file::write_secret(path, contents.as_bytes(), force)
+                        .map_err(|e| output_error(path, e))?;
+                    output::print_saved(
+                        "OTP secret and URI",
+                        path,
+                        quiet || format != Format::Text,
+                    );
+                }
+                (None, Format::Text) => output::print_otp_secret(&secret, &uri, quiet),
+                (None, _) => output::print_records(format, &[record]),
+            }
+            Ok(())
+        }
+        Command::Otp {
+            action:
+                OtpAction::Code {
+                    secret,
+                    time,
+                    counter,
+                    digits,
+                    period,
+                    algorithm,
+                    quiet,
+                },
+        } => {
+            let input = Zeroizing::new(match secret {
+                Some(secret) => secret,
+                None => read_stdin_line(Error::InvalidOtp)?,
+            });
+            let (secret, mut params) = if input.starts_with("otpauth://") {
+                otp::parse_uri(&input)?
+            } else {
+                (input, OtpParams::default())
             };
-            run_generate(count, copy, show_entropy, quiet, || {
-                passphrase::generate_passphrase(&opts)
+            if let Some(counter) = counter {
+                params.kind = OtpKind::Hotp { counter };
+            }
+            if let (Some(period), OtpKind::Totp { .. }) = (period, params.kind) {
+                params.kind = OtpKind::Totp { period };
+            }
+            if let Some(digits) = digits {
+                params.digits = digits;
+            }
+            if let Some(algorithm) = algorithm {
+                params.algorithm = algorithm.parse()?;
+            }
+            params.validate()?;
+            let key = otp::decode_secret(&secret)?;
+            let now = time.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs())
             });
+            let code = params.code(&key, now);
+            match format {
+                Format::Text => output::print_otp_code(&code, params.remaining(now), quiet),
+                _ => output::print_records(
+                    format,
+                    &[Record::new()
+                        .with("code", code)
+                        .with("remaining", params.remaining(now))],
+                ),
+            }
+            Ok(())
         }
-        Some(Command::Profile {
+        Command::Profile {
             name,
+            length,
+            custom_symbols,
+            keyboard_safe,
+            ssid,
+            qr,
+            qr_output,
+            emit: flags,
+        } => {
+            let prof = customized_profile(&name, length, custom_symbols, &keyboard_safe)?;
+            if !prof.wpa {
+                return Err(Error::QrCode(format!(
+                    "QR codes are only made for WPA key profiles such as wifi, not {}",
+                    prof.name
+                )));
+            }
+            let ssid = ssid.unwrap_or_default();
+            let run = run.with(flags);
+            let mut rng = open_rng(seed, flags.copy, source)?;
+            let key = emit(&run, &mut *rng, &prof)?;
+            let code = qr::encode(&wifi::payload(&ssid, &key)?)?;
+            if qr {
+                output::print_qr(&qr::render_terminal(&code), flags.quiet);
+            }
+            if let Some(path) = qr_output {
+                qr::write_image(&code, &path, force)?;
+            }
+            Ok(())
+        }
+        Command::Profiles => {
+            let profiles = profile::get_profiles();
+            match format {
+                Format::Text => {
+                    println!("Available profiles:");
+                    println!();
+                    for p in profiles {
+                        println!("  {:<12} {}", p.name, p.description);
+                    }
+                }
+                _ => {
+                    let records: Vec<Record> =
+                        profiles.iter().map(output::profile_record).collect();
+                    output::print_records(format, &records);
+                }
+            }
+            Ok(())
+        }
+        Command::Derive {
+            site,
+            login,
+            counter,
+            profile: profile_name,
+            length,
+            no_uppercase,
+            no_lowercase,
+            no_digits,
+            no_symbols,
+            no_ambiguous,
             copy,
             entropy: show_entropy,
             quiet,
-            count,
-        }) => {
-            let prof = match profile::find_profile(&name) {
-                Some(p) => p,
+        } => {
+            if seed.is_some() {
+                return Err(Error::InvalidSeed(
+                    "pw derive is deterministic already, --seed does not apply".to_string(),
+                ));
+            }
+            let generator: Box<dyn Generator> = match profile_name {
+                Some(name) => {
+                    let prof = profile::find_profile(&name).ok_or(Error::UnknownProfile(name))?;
+                    if !prof.is_deterministic() {
+                        return Err(Error::NotDeterministic(prof.name));
+                    }
+                    Box::new(prof)
+                }
                 None => {
-                    eprintln!("Unknown profile: {name}");
-                    eprintln!("Use 'pw profiles' to list available profiles.");
-                    std::process::exit(1);
+                    let builder = CharsetBuilder::new()
+                        .uppercase(!no_uppercase)
+                        .lowercase(!no_lowercase)
+                        .digits(!no_digits)
+                        .symbols(!no_symbols)
+                        .no_ambiguous(no_ambiguous);
+                    Box::new(RandomPassword::new(
+                        builder,
+                        length,
+                        PasswordConstraints::default(),
+                    )?)
                 }
             };
-            run_generate(count, copy, show_entropy, quiet, || prof.generate());
-        }
-        Some(Command::Profiles) => {
-            println!("Available profiles:");
-            println!();
-            for p in profile::get_profiles() {
-                println!("  {:<12} {}", p.name, p.description);
-            }
-        }
-        None => {
-            let charset = CharsetBuilder::new()
-                .uppercase(!cli.no_uppercase)
-                .lowercase(!cli.no_lowercase)
-                .digits(!cli.no_digits)
-                .symbols(!cli.no_symbols)
-                .no_ambiguous(cli.no_ambiguous)
-                .custom_symbols(cli.custom_symbols.clone())
-                .build();
-
-            let length = cli.length;
-            run_generate(cli.count, cli.copy, cli.entropy, cli.quiet, || {
-                random::generate_password(&charset, length)
-            });
+
+            let master = read_secret("Master secret: ")?;
+            let params = DeriveParams {
+                site,
+                login,
+                counter,
+            };
+            let mut derived = derive::derive_source(&master, &params)?;
+            let run = RunOptions {
+                count: 1,
+                copy,
+                show_entropy,
+                quiet,
+                // The derived source must yield the same password every time
+                threads: 1,
+                ..run
+            };
+            emit(&run, &mut derived, generator.as_ref()).map(drop)
+        }
+        Command::Check { password, quiet } => {
+            let policy = policy.ok_or_else(|| {
+                Error::InvalidPolicy("no policy given, use --policy <FILE|PRESET>".to_string())
+            })?;
+            let password = match password {
+                Some(password) => password,
+                None => read_stdin_line(Error::InvalidPolicy)?,
+            };
+            let report = policy.report(&password);
+            match format {
+                Format::Text => output::print_policy_report(policy.display_name(), &report, quiet),
+                _ => {
+                    let records: Vec<Record> = report
+                        .iter()
+                        .map(|result| output::rule_record(policy.display_name(), result))
+                        .collect();
+                    output::print_records(format, &records);
+                }
+            }
+            let violations = policy.violations(&password);
+            if violations.is_empty() {
+                Ok(())
+            } else {
+                Err(Error::PolicyViolation(violations))
+            }
         }
+        _ => unreachable!("generating commands are dispatched above"),
     }
 }
 
-fn run_generate(
+/// The password generator of `pw` without a subcommand, with the policy
+/// folded in.
+fn password_generator(cli: &Cli, policy: Option<&Policy>) -> Result<RandomPassword> {
+    // Fold the policy into the generator so that most candidates
+    // already comply; the remaining rules are enforced by retrying.
+    let rules = policy.cloned().unwrap_or_default();
+    let mut exclude = cli.exclude.clone().unwrap_or_default();
+    exclude.push_str(&rules.forbidden_chars);
+    let mut builder = CharsetBuilder::new()
+        .uppercase(!cli.no_uppercase)
+        .lowercase(!cli.no_lowercase)
+        .digits(!cli.no_digits)
+        .symbols(!cli.no_symbols)
+        .no_ambiguous(cli.no_ambiguous)
+        .custom_symbols(cli.custom_symbols.clone())
+        .include(cli.include.clone())
+        .exclude((!exclude.is_empty()).then_some(exclude))
+        .min_uppercase(cli.min_uppercase.max(rules.min_uppercase))
+        .min_lowercase(cli.min_lowercase.max(rules.min_lowercase))
+        .min_digits(cli.min_digits.max(rules.min_digits))
+        .min_symbols(cli.min_symbols.max(rules.min_symbols));
+    for class in &cli.unicode {
+        builder = builder.unicode_class(class.parse()?);
+    }
+    let layouts = cli
+        .keyboard_safe
+        .iter()
+        .map(|layout| layout.parse())
+        .collect::<Result<_>>()?;
+    builder = builder.keyboard_safe(layouts);
+
+    let constraints = PasswordConstraints {
+        no_repeat: stricter(cli.no_repeat, rules.max_repeat.map(|n| n + 1)),
+        no_sequential: stricter(cli.no_sequential, rules.max_sequential.map(|n| n + 1)),
+        unique_chars: cli.unique_chars,
+    };
+    rules.check_length(cli.length)?;
+    let generator = RandomPassword::new(builder, cli.length, constraints)?;
+    warn_duplicates(generator.charset());
+    Ok(generator)
+}
+
+/// Warns about characters that `--symbols`, `--include` or `--unicode`
+/// repeat, or that another class already has.
+fn warn_duplicates(charset: &Charset) {
+    let duplicates = charset.duplicates();
+    if !duplicates.is_empty() {
+        let duplicates: String = duplicates.iter().collect();
+        output::print_warning(&format!("duplicate characters ignored: {duplicates}"));
+    }
+}
+
+/// The generator of a command that only generates secrets, and its output
+/// flags, or `None` for every other command.
+fn generator(command: &Command) -> Result<Option<(Box<dyn Generator>, Emit)>> {
+    let generator: Box<dyn Generator> = match command {
+        Command::Passphrase {
+            words,
+            separator,
+            capitalize,
+            append_digit,
+            ..
+        } => Box::new(PassphraseOptions {
+            words: *words,
+            separator: separator.clone(),
+            capitalize: *capitalize,
+            append_digit: *append_digit,
+        }),
+        Command::Mask {
+            pattern,
+            custom1,
+            custom2,
+            custom3,
+            custom4,
+            ..
+        } => Box::new(Mask::parse(
+            pattern,
+            &[
+                custom1.clone(),
+                custom2.clone(),
+                custom3.clone(),
+                custom4.clone(),
+            ],
+        )?),
+        Command::Pronounceable {
+            length,
+            digits,
+            symbols,
+            capitalize,
+            ..
+        } => {
+            let opts = PronounceableOptions {
+                length: *length,
+                digits: *digits,
+                symbols: *symbols,
+                capitalize: *capitalize,
+            };
+            opts.validate()?;
+            Box::new(opts)
+        }
+        Command::Token {
+            bytes, encoding, ..
+        } => {
+            let opts = TokenOptions {
+                bytes: *bytes,
+                encoding: encoding.parse()?,
+            };
+            opts.encoding.check(*bytes)?;
+            Box::new(opts)
+        }
+        Command::Apikey {
+            action: None,
+            prefix,
+            length,
+            checksum,
+            ..
+        } => {
+            let opts = ApiKeyOptions {
+                prefix: prefix.clone(),
+                length: *length,
+                checksum: checksum.parse()?,
+            };
+            opts.validate()?;
+            Box::new(opts)
+        }
+        Command::Id {
+            id_format,
+            uppercase,
+            braced,
+            urn,
+            alphabet,
+            size,
+            time,
+            inspect: None,
+            ..
+        } => {
+            let opts = IdOptions {
+                format: id_format.parse()?,
+                uppercase: *uppercase,
+                braced: *braced,
+                urn: *urn,
+                alphabet: alphabet.clone(),
+                size: *size,
+                time_ms: *time,
+            };
+            opts.validate()?;
+            Box::new(opts)
+        }
+        Command::Profile {
+            name,
+            length,
+            custom_symbols,
+            keyboard_safe,
+            qr: false,
+            qr_output: None,
+            ..
+        } => Box::new(customized_profile(
+            name,
+            *length,
+            custom_symbols.clone(),
+            keyboard_safe,
+        )?),
+        _ => return Ok(None),
+    };
+    let emit = match command {
+        Command::Passphrase { emit, .. }
+        | Command::Mask { emit, .. }
+        | Command::Pronounceable { emit, .. }
+        | Command::Token { emit, .. }
+        | Command::Apikey { emit, .. }
+        | Command::Id { emit, .. }
+        | Command::Profile { emit, .. } => *emit,
+        _ => unreachable!("only generating commands get this far"),
+    };
+    Ok(Some((generator, emit)))
+}
+
+/// The profile `name` with the overrides of `pw profile` applied.
+fn customized_profile(
+    name: &str,
+    length: Option<usize>,
+    symbols: Option<String>,
+    keyboard_safe: &[String],
+) -> Result<Profile> {
+    let mut prof =
+        profile::find_profile(name).ok_or_else(|| Error::UnknownProfile(name.to_string()))?;
+    let layouts = keyboard_safe
+        .iter()
+        .map(|layout| layout.parse())
+        .collect::<Result<_>>()?;
+    prof.customize(length, symbols, layouts)?;
+    if let Some(charset) = prof.charset() {
+        warn_duplicates(charset);
+    }
+    Ok(prof)
+}
+
+/// Output settings shared by every generating command.
+#[derive(Clone, Copy)]
+struct RunOptions<'a> {
     count: usize,
     copy: bool,
     show_entropy: bool,
     quiet: bool,
-    generate: impl Fn() -> String,
-) {
-    let mut last = String::new();
+    policy: Option<&'a Policy>,
+    /// Hex seed for reproducible, insecure output.
+    seed: Option<&'a str>,
+    source: &'a RngKind,
+    threads: usize,
+    unique: bool,
+    /// File or directory that receives the secrets instead of stdout.
+    output: Option<&'a Path>,
+    force: bool,
+    format: Format,
+    /// Language that secrets are written as literals of.
+    escape: Option<Escape>,
+}
 
-    for i in 0..count {
-        let password = generate();
-        output::print_password(&password, show_entropy, quiet);
-        if !quiet && count > 1 && i < count - 1 && show_entropy {
-            println!();
+impl RunOptions<'_> {
+    /// These settings with the output flags of one command.
+    fn with(&self, emit: Emit) -> Self {
+        RunOptions {
+            count: emit.count,
+            copy: emit.copy,
+            show_entropy: emit.entropy,
+            quiet: emit.quiet,
+            ..*self
         }
-        last = password;
+    }
+}
+
+fn run_generate(run: &RunOptions, generator: &dyn Generator) -> Result<()> {
+    if run.count == 0 && (run.copy || run.unique || run.output.is_some()) {
+        return Err(Error::InvalidCount);
+    }
+    let mut rng = open_rng(run.seed, run.copy, run.source)?;
+    emit(run, &mut *rng, generator).map(drop)
+}
+
+fn open_rng(seed: Option<&str>, copy: bool, source: &RngKind) -> Result<Box<dyn RandomSource>> {
+    match seed {
+        Some(_) if copy => Err(Error::SeedWithCopy),
+        Some(seed) => {
+            let rng = rng::seeded(seed)?;
+            output::print_warning("seeded output is predictable, never use it as a real secret");
+            Ok(Box::new(rng))
+        }
+        None => source.open(),
+    }
+}
+
+/// Generates and prints the passwords of `run` with `generator`, drawing
+/// from `rng`, and returns the last one. A count of 0 streams until the
+/// reader closes the pipe.
+fn emit(
+    run: &RunOptions,
+    rng: &mut dyn RandomSource,
+    generator: &dyn Generator,
+) -> Result<Zeroizing<String>> {
+    let RunOptions {
+        count,
+        copy,
+        show_entropy,
+        quiet,
+        policy,
+        threads,
+        unique,
+        output: output_path,
+        force,
+        format,
+        escape,
+        ..
+    } = *run;
+    let structured = format != Format::Text;
+    // Notices would get in the way of machine-readable output
+    let quiet = quiet || structured;
+    // Counting constrained passwords can be costly, so only when shown
+    let bits = if show_entropy && !quiet {
+        if !generator.entropy_is_exact() {
+            output::print_entropy_bound_notice();
+        }
+        generator.entropy()
+    } else {
+        None
+    };
+    let escaped = |password: &str| escape.map(|escape| Zeroizing::new(escape.escape(password)));
+    let text = |out: &mut dyn Write, password: &str| {
+        output::write_password(out, password, escape, show_entropy, quiet, bits)
+    };
+    let line = |out: &mut dyn Write, password: &str| match escaped(password) {
+        Some(literal) => batch::line(out, &literal),
+        None => batch::line(out, password),
+    };
+    let record = |out: &mut dyn Write, password: &str| {
+        let mut record = output::secret_record(password, generator);
+        if let Some(literal) = escaped(password) {
+            record = record.with("escaped", literal.as_str());
+        }
+        let record = Zeroizing::new(format.record(&record));
+        out.write_all(record.as_bytes())
+    };
+    let mut keys = output::SECRET_FIELDS.to_vec();
+    if escape.is_some() {
+        keys.push("escaped");
+    }
+    let header = format.header(&keys);
+    let mut batch = Batch {
+        generator,
+        count: (count > 0).then_some(count as u64),
+        threads,
+        policy,
+        unique,
+        format: if structured { &record } else { &text },
+        separator: match format {
+            Format::Text if show_entropy && !quiet => b"\n",
+            _ => format.separator().as_bytes(),
+        },
+        header: header.as_bytes(),
+        footer: format.footer().as_bytes(),
+    };
+    let summary = match output_path {
+        None => batch.write(rng, std::io::stdout().lock())?,
+        Some(path) => {
+            if !structured {
+                // Files get the secrets alone, one per line
+                batch.format = &line;
+                batch.separator = b"";
+            }
+            if path.is_dir() {
+                write_directory(&batch, rng, path, force)?
+            } else {
+                let fail = |e| output_error(path, e);
+                let mut file = SecretFile::create(path, force).map_err(fail)?;
+                let summary = batch.write(rng, &mut file)?;
+                file.commit().map_err(fail)?;
+                summary
+            }
+        }
+    };
+    let last = summary.last.unwrap_or_default();
+    if summary.closed {
+        return Ok(last);
+    }
+    if let Some(path) = output_path {
+        let what = match summary.written {
+            1 => "1 secret".to_string(),
+            n => format!("{n} secrets"),
+        };
+        output::print_saved(&what, path, quiet);
+        if show_entropy && !quiet {
+            output::print_entropy(&last, bits);
+        }
+    }
+    if unique {
+        output::print_unique_report(summary.written, summary.rejected);
     }
 
     if copy {
+        let last = escaped(&last).unwrap_or_else(|| last.clone());
         match clipboard::copy_to_clipboard(&last) {
             Ok(()) => output::print_copy_notice(quiet),
             Err(e) => eprintln!("  {e}"),
         }
     }
+
+    Ok(last)
+}
+
+/// Writes each secret of `batch` to its own file in `dir`, named after its
+/// position in the batch, with the header and footer of the batch.
+fn write_directory(
+    batch: &Batch,
+    rng: &mut dyn RandomSource,
+    dir: &Path,
+    force: bool,
+) -> Result<batch::Summary> {
+    let width = batch.count.unwrap_or(1).to_string().len();
+    let next = AtomicU64::new(1);
+    let format = |_: &mut dyn Write, secret: &str| {
+        let mut contents = Zeroizing::new(batch.header.to_vec());
+        (batch.format)(&mut *contents, secret)?;
+        contents.extend_from_slice(batch.footer);
+        let path = dir.join(format!("{:0width$}", next.fetch_add(1, Ordering::Relaxed)));
+        file::write_secret(&path, &contents, force)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+    };
+    let batch = Batch {
+        format: &format,
+        separator: b"",
+        header: b"",
+        footer: b"",
+        ..*batch
+    };
+    batch.write(rng, std::io::sink())
+}
+
+fn output_error(path: &Path, e: std::io::Error) -> Error {
+    Error::Output(format!("{}: {e}", path.display()))
+}
+
+/// The tighter of two "forbid N in a row" limits.
+fn stricter(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Reads a secret from the terminal without echo, or from the first line
+/// of stdin when it is not a terminal.
+fn read_secret(prompt: &str) -> Result<Zeroizing<String>> {
+    let secret = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(prompt)
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| line)
+    };
+    let mut secret =
+        Zeroizing::new(secret.map_err(|e| Error::InvalidSecret(format!("cannot read it: {e}")))?);
+    let trimmed = secret.trim_end_matches(['\n', '\r']).len();
+    secret.truncate(trimmed);
+    Ok(secret)
+}
+
+/// Reads the first line of stdin, reporting failures as `error`.
+fn read_stdin_line(error: fn(String) -> Error) -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| error(format!("cannot read stdin: {e}")))?;
+    Ok(line.trim_end_matches(['\n', '\r']).to_string())
 }
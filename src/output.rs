@@ -1,18 +1,137 @@
+use std::any::Any;
+use std::io::{self, Write};
+use std::path::Path;
+
 use colored::Colorize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use pw::entropy::{self, EntropyInfo};
+use pw::escape::Escape;
+use pw::format::{Format, Record, Value};
+use pw::generator::Generator;
+use pw::generator::id::{self, IdInfo};
+use pw::generator::keypair::KeyType;
+use pw::policy::RuleResult;
+use pw::profile::Profile;
+use zeroize::Zeroizing;
 
-use crate::entropy;
+/// Fields of [`secret_record`], for CSV and TSV headers.
+pub const SECRET_FIELDS: [&str; 8] = [
+    "secret",
+    "kind",
+    "profile",
+    "length",
+    "alphabet",
+    "entropy",
+    "score",
+    "crack_time",
+];
 
-pub fn print_password(password: &str, show_entropy: bool, quiet: bool) {
+/// Writes a password. When `bits` is given it replaces the zxcvbn estimate
+/// as the reported entropy, for generators whose search space is known.
+pub fn write_password(
+    out: &mut dyn Write,
+    password: &str,
+    escape: Option<Escape>,
+    show_entropy: bool,
+    quiet: bool,
+    bits: Option<f64>,
+) -> io::Result<()> {
+    let shown = escape.map(|escape| Zeroizing::new(escape.escape(password)));
+    let shown = shown.as_deref().map_or(password, String::as_str);
     if quiet {
-        println!("{password}");
-        return;
+        return writeln!(out, "{shown}");
     }
 
-    println!("  {}", password.bold());
+    writeln!(out, "  {}", shown.bold())?;
 
     if show_entropy {
-        let info = entropy::analyze_password(password);
-        println!("{}", entropy::format_entropy_display(&info));
+        writeln!(out, "{}", entropy_display(password, bits))?;
+    }
+    Ok(())
+}
+
+/// Prints the strength of a password without the password itself.
+pub fn print_entropy(password: &str, bits: Option<f64>) {
+    println!("{}", entropy_display(password, bits));
+}
+
+fn entropy_display(password: &str, bits: Option<f64>) -> String {
+    let mut info = entropy::analyze_password(password);
+    if let Some(bits) = bits {
+        info.bits = bits;
+    }
+    format_entropy_display(&info)
+}
+
+fn format_entropy_display(info: &EntropyInfo) -> String {
+    let bar = strength_bar(info.score);
+    let label = strength_label(info.score);
+    format!(
+        "  Entropy: {:.1} bits | Strength: {} {} | Crack time: {}",
+        info.bits, bar, label, info.crack_time
+    )
+}
+
+fn strength_bar(score: u8) -> String {
+    let filled = score as usize + 1;
+    let empty = 4_usize.saturating_sub(score as usize);
+    let bar_filled: String = (0..filled).map(|_| '\u{2588}').collect(); // Full block
+    let bar_empty: String = (0..empty).map(|_| '\u{2591}').collect(); // Light shade
+
+    match score {
+        0 => format!("{}{}", bar_filled.red(), bar_empty.dimmed()),
+        1 => format!("{}{}", bar_filled.red(), bar_empty.dimmed()),
+        2 => format!("{}{}", bar_filled.yellow(), bar_empty.dimmed()),
+        3 => format!("{}{}", bar_filled.green(), bar_empty.dimmed()),
+        4 => format!("{}{}", bar_filled.bright_green(), bar_empty.dimmed()),
+        _ => format!("{}{}", bar_filled, bar_empty),
+    }
+}
+
+fn strength_label(score: u8) -> String {
+    let label = entropy::strength_label(score);
+    match score {
+        0 | 1 => label.red().to_string(),
+        2 => label.yellow().to_string(),
+        3 => label.green().to_string(),
+        4 => label.bright_green().to_string(),
+        _ => label.to_string(),
+    }
+}
+
+pub fn print_warning(message: &str) {
+    eprintln!("{} {message}", "Warning:".yellow());
+}
+
+/// Reports on stderr, so that piped output stays clean.
+pub fn print_unique_report(written: u64, rejected: u64) {
+    let duplicates = if rejected == 1 {
+        "duplicate"
+    } else {
+        "duplicates"
+    };
+    eprintln!(
+        "{}",
+        format!("  {written} distinct, {rejected} {duplicates} rejected").dimmed()
+    );
+}
+
+/// Warns on stderr that the entropy shown is a lower bound.
+pub fn print_entropy_bound_notice() {
+    eprintln!(
+        "{}",
+        "  Entropy is a lower bound: the exact count would take too long".dimmed()
+    );
+}
+
+/// Tells where secrets went, without echoing them.
+pub fn print_saved(what: &str, path: &Path, quiet: bool) {
+    if !quiet {
+        println!(
+            "{}",
+            format!("  Wrote {what} to {}", path.display()).dimmed()
+        );
     }
 }
 
@@ -21,3 +140,183 @@ pub fn print_copy_notice(quiet: bool) {
         println!("{}", "  Copied to clipboard.".dimmed());
     }
 }
+
+/// Prints records in a machine-readable format.
+pub fn print_records(format: Format, records: &[Record]) {
+    print!("{}", format.records(records));
+}
+
+/// A generated secret with what is known of its strength: the theoretical
+/// entropy of the generator, and the zxcvbn score and crack time.
+pub fn secret_record(secret: &str, generator: &dyn Generator) -> Record {
+    let info = entropy::analyze_password(secret);
+    let generator_any: &dyn Any = generator;
+    let profile = generator_any
+        .downcast_ref::<Profile>()
+        .map(|p| p.name.clone());
+    Record::new()
+        .with("secret", secret)
+        .with("kind", generator.kind())
+        .with("profile", profile)
+        .with("length", secret.graphemes(true).count())
+        .with("alphabet", generator.alphabet())
+        .with("entropy", generator.entropy())
+        .with("score", info.score as u64)
+        .with("crack_time", info.crack_time)
+}
+
+pub fn profile_record(profile: &Profile) -> Record {
+    Record::new()
+        .with("name", profile.name.clone())
+        .with("kind", profile.kind())
+        .with("description", profile.describe())
+        .with("alphabet", profile.alphabet())
+        .with("entropy", profile.entropy())
+        .with("parameters", Value::Map(profile.parameters()))
+}
+
+pub fn id_record(info: &IdInfo) -> Record {
+    Record::new()
+        .with("format", info.format.clone())
+        .with("timestamp", info.timestamp_ms.map(id::format_timestamp))
+        .with("timestamp_ms", info.timestamp_ms)
+        .with("random", info.random.clone())
+        .with("random_bits", info.random_bits.map(u64::from))
+}
+
+pub fn rule_record(policy: &str, result: &RuleResult) -> Record {
+    Record::new()
+        .with("rule", result.rule.clone())
+        .with("passed", result.passed)
+        .with("detail", result.detail.clone())
+        .with("policy", policy)
+}
+
+/// Prints a key pair, or only its public half once the private one has
+/// been written to a file.
+pub fn print_keypair(
+    private: Option<&str>,
+    public: &str,
+    key_type: KeyType,
+    quiet: bool,
+    format: Format,
+) {
+    if format != Format::Text {
+        let record = match private {
+            Some(private) => Record::new().with("private", private),
+            None => Record::new(),
+        };
+        let record = record
+            .with("public", public)
+            .with("type", key_type.to_string());
+        return print_records(format, &[record]);
+    }
+    if quiet {
+        if let Some(private) = private {
+            println!("{private}");
+        }
+        println!("{public}");
+        return;
+    }
+
+    if let Some(private) = private {
+        println!("  {}", "Private key".dimmed());
+        for line in private.lines() {
+            println!("  {}", line.bold());
+        }
+    }
+    println!("  {}", "Public key".dimmed());
+    println!("  {public}");
+}
+
+/// Prints a new OTP secret and its enrollment URI.
+pub fn print_otp_secret(secret: &str, uri: &str, quiet: bool) {
+    if quiet {
+        println!("{secret}");
+        println!("{uri}");
+        return;
+    }
+
+    println!("  {}", "Secret".dimmed());
+    println!("  {}", secret.bold());
+    println!("  {}", "URI".dimmed());
+    println!("  {uri}");
+}
+
+/// Prints a one-time code, with the seconds left for TOTP codes.
+pub fn print_otp_code(code: &str, remaining: Option<u64>, quiet: bool) {
+    if quiet {
+        println!("{code}");
+        return;
+    }
+
+    match remaining {
+        Some(seconds) => println!(
+            "  {}  {}",
+            code.bold(),
+            format!("({seconds}s left)").dimmed()
+        ),
+        None => println!("  {}", code.bold()),
+    }
+}
+
+/// Prints QR code lines as dark modules on a light background, whatever the
+/// terminal theme.
+pub fn print_qr(lines: &[String], quiet: bool) {
+    if !quiet {
+        println!();
+    }
+    for line in lines {
+        println!("{}", line.black().on_white());
+    }
+}
+
+/// Prints a passed offline check, such as an API key checksum.
+pub fn print_verified(message: &str, quiet: bool) {
+    if !quiet {
+        println!("  {} {message}", "\u{2713}".green());
+    }
+}
+
+/// Prints one line per policy rule, marking each as passed or failed.
+pub fn print_policy_report(name: &str, results: &[RuleResult], quiet: bool) {
+    if quiet {
+        return;
+    }
+
+    println!("  {}", name.bold());
+    for result in results {
+        let mark = if result.passed {
+            "\u{2713}".green()
+        } else {
+            "\u{2717}".red()
+        };
+        println!(
+            "  {mark} {} {}",
+            result.rule,
+            format!("({})", result.detail).dimmed()
+        );
+    }
+}
+
+/// Prints the fields decoded by `pw id --inspect`.
+pub fn print_id_info(info: &IdInfo) {
+    println!("  {:<10} {}", "Format", info.format.bold());
+    if let Some(ms) = info.timestamp_ms {
+        println!(
+            "  {:<10} {} {}",
+            "Timestamp",
+            id::format_timestamp(ms),
+            format!("({ms} ms)").dimmed()
+        );
+    }
+    match (&info.random, info.random_bits) {
+        (Some(random), Some(bits)) => println!(
+            "  {:<10} {random} {}",
+            "Random",
+            format!("({bits} bits)").dimmed()
+        ),
+        (None, Some(bits)) => println!("  {:<10} {bits} bits", "Random"),
+        _ => {}
+    }
+}
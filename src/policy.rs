@@ -0,0 +1,314 @@
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::entropy;
+use crate::error::{Error, Result};
+use crate::generator::random;
+
+const PRESETS: &[(&str, &str)] = &[
+    (
+        "nist-800-63b",
+        include_str!("../data/policies/nist-800-63b.toml"),
+    ),
+    (
+        "ad-complexity",
+        include_str!("../data/policies/ad-complexity.toml"),
+    ),
+];
+
+/// Regeneration attempts before giving up on a policy.
+const MAX_ATTEMPTS: usize = 1000;
+
+/// An organization password policy, loaded from TOML.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    pub name: Option<String>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub min_uppercase: usize,
+    pub min_lowercase: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+    /// Minimum number of distinct classes among uppercase, lowercase,
+    /// digits and symbols.
+    pub min_classes: usize,
+    pub forbidden_chars: String,
+    /// Longest allowed run of identical characters.
+    pub max_repeat: Option<usize>,
+    /// Longest allowed run of ascending letters or digits.
+    pub max_sequential: Option<usize>,
+    /// Minimum zxcvbn score (0-4).
+    pub min_score: Option<u8>,
+}
+
+/// The outcome of one policy rule for a given password.
+pub struct RuleResult {
+    pub rule: String,
+    pub detail: String,
+    pub passed: bool,
+}
+
+impl Policy {
+    /// Loads a built-in preset by name, or else a TOML file at `spec`.
+    pub fn load(spec: &str) -> Result<Self> {
+        if let Some((_, source)) = PRESETS.iter().find(|(name, _)| *name == spec) {
+            return Self::parse(source);
+        }
+        let source = std::fs::read_to_string(spec).map_err(|e| {
+            Error::InvalidPolicy(format!("{spec}: {e} (presets: {})", preset_names()))
+        })?;
+        let mut policy = Self::parse(&source)?;
+        policy.name.get_or_insert_with(|| spec.to_string());
+        Ok(policy)
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let policy: Self =
+            toml::from_str(source).map_err(|e| Error::InvalidPolicy(e.message().to_string()))?;
+        if let (Some(min), Some(max)) = (policy.min_length, policy.max_length)
+            && min > max
+        {
+            return Err(Error::InvalidPolicy(format!(
+                "min_length {min} is greater than max_length {max}"
+            )));
+        }
+        if policy.min_classes > 4 {
+            return Err(Error::InvalidPolicy(
+                "min_classes cannot be more than 4".to_string(),
+            ));
+        }
+        if policy.max_repeat == Some(0) || policy.max_sequential == Some(0) {
+            return Err(Error::InvalidPolicy(
+                "max_repeat and max_sequential must be at least 1".to_string(),
+            ));
+        }
+        if policy.min_score.is_some_and(|s| s > 4) {
+            return Err(Error::InvalidPolicy(
+                "min_score must be between 0 and 4".to_string(),
+            ));
+        }
+        Ok(policy)
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("custom policy")
+    }
+
+    /// Checks that a generator producing `length` characters can comply.
+    pub fn check_length(&self, length: usize) -> Result<()> {
+        let too_short = self.min_length.is_some_and(|min| length < min);
+        let too_long = self.max_length.is_some_and(|max| length > max);
+        if too_short || too_long {
+            return Err(Error::PolicyViolation(vec![self.length_rule()]));
+        }
+        Ok(())
+    }
+
+    /// Evaluates every rule of the policy against `password`.
+    pub fn report(&self, password: &str) -> Vec<RuleResult> {
+        let chars: Vec<char> = password.chars().collect();
+        let count = |f: fn(&char) -> bool| chars.iter().filter(|c| f(c)).count();
+        let uppercase = count(|c| c.is_uppercase());
+        let lowercase = count(|c| c.is_lowercase());
+        let digits = count(|c| c.is_numeric());
+        let symbols = count(|c| !c.is_alphanumeric());
+        let mut results = Vec::new();
+
+        let length = password.graphemes(true).count();
+        if self.min_length.is_some() || self.max_length.is_some() {
+            results.push(RuleResult {
+                rule: self.length_rule(),
+                detail: format!("has {length}"),
+                passed: self.min_length.is_none_or(|min| length >= min)
+                    && self.max_length.is_none_or(|max| length <= max),
+            });
+        }
+
+        for (min, name, found) in [
+            (self.min_uppercase, "uppercase letters", uppercase),
+            (self.min_lowercase, "lowercase letters", lowercase),
+            (self.min_digits, "digits", digits),
+            (self.min_symbols, "symbols", symbols),
+        ] {
+            if min > 0 {
+                results.push(RuleResult {
+                    rule: format!("At least {min} {name}"),
+                    detail: format!("has {found}"),
+                    passed: found >= min,
+                });
+            }
+        }
+
+        if self.min_classes > 0 {
+            let classes = [uppercase, lowercase, digits, symbols]
+                .iter()
+                .filter(|&&n| n > 0)
+                .count();
+            results.push(RuleResult {
+                rule: format!(
+                    "At least {} of uppercase, lowercase, digits and symbols",
+                    self.min_classes
+                ),
+                detail: format!("has {classes}"),
+                passed: classes >= self.min_classes,
+            });
+        }
+
+        if !self.forbidden_chars.is_empty() {
+            let mut found: Vec<char> = chars
+                .iter()
+                .copied()
+                .filter(|c| self.forbidden_chars.contains(*c))
+                .collect();
+            found.sort_unstable();
+            found.dedup();
+            results.push(RuleResult {
+                rule: format!("None of {:?}", self.forbidden_chars),
+                detail: if found.is_empty() {
+                    "none found".to_string()
+                } else {
+                    format!("contains {:?}", found.iter().collect::<String>())
+                },
+                passed: found.is_empty(),
+            });
+        }
+
+        let runs = random::longest_runs(&chars);
+        if let Some(max) = self.max_repeat {
+            results.push(RuleResult {
+                rule: format!("No more than {max} identical characters in a row"),
+                detail: format!("longest run is {}", runs.repeat),
+                passed: runs.repeat <= max,
+            });
+        }
+        if let Some(max) = self.max_sequential {
+            results.push(RuleResult {
+                rule: format!("No more than {max} ascending letters or digits in a row"),
+                detail: format!("longest run is {}", runs.sequential),
+                passed: runs.sequential <= max,
+            });
+        }
+
+        if let Some(min) = self.min_score {
+            let score = entropy::analyze_password(password).score;
+            results.push(RuleResult {
+                rule: format!("Not common or guessable (strength score of at least {min}/4)"),
+                detail: format!("score is {score}"),
+                passed: score >= min,
+            });
+        }
+
+        results
+    }
+
+    /// The rules `password` fails.
+    pub fn violations(&self, password: &str) -> Vec<String> {
+        self.report(password)
+            .into_iter()
+            .filter(|r| !r.passed)
+            .map(|r| format!("{} ({})", r.rule, r.detail))
+            .collect()
+    }
+
+    /// Calls `generate` until it returns a compliant password. Outputs that
+    /// fail are discarded, never shown.
+    pub fn enforce(&self, mut generate: impl FnMut() -> Result<String>) -> Result<String> {
+        let mut violations = Vec::new();
+        for _ in 0..MAX_ATTEMPTS {
+            let password = generate()?;
+            violations = self.violations(&password);
+            if violations.is_empty() {
+                return Ok(password);
+            }
+        }
+        Err(Error::PolicyViolation(violations))
+    }
+
+    fn length_rule(&self) -> String {
+        match (self.min_length, self.max_length) {
+            (Some(min), Some(max)) => format!("Between {min} and {max} characters"),
+            (Some(min), None) => format!("At least {min} characters"),
+            (None, Some(max)) => format!("At most {max} characters"),
+            (None, None) => "Any length".to_string(),
+        }
+    }
+}
+
+fn preset_names() -> String {
+    PRESETS
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_load() {
+        for (name, _) in PRESETS {
+            assert!(Policy::load(name).is_ok(), "preset {name} failed to load");
+        }
+    }
+
+    #[test]
+    fn ad_complexity_needs_three_classes() {
+        let policy = Policy::load("ad-complexity").unwrap();
+        assert!(policy.violations("Abcdef1").is_empty());
+        assert_eq!(policy.violations("abcdef12").len(), 1);
+        assert_eq!(policy.violations("Ab1").len(), 1);
+    }
+
+    #[test]
+    fn nist_rejects_repetitive_and_common() {
+        let policy = Policy::load("nist-800-63b").unwrap();
+        assert!(policy.violations("correct-horse-battery-staple").is_empty());
+        assert!(!policy.violations("password").is_empty());
+        assert!(!policy.violations("Xk9#aaaa-mQ2!vZ").is_empty());
+        assert!(!policy.violations("Xk9#1234-mQ2!vZ").is_empty());
+    }
+
+    #[test]
+    fn report_explains_each_rule() {
+        let policy = Policy::parse(
+            r#"
+            min_length = 10
+            min_digits = 2
+            forbidden_chars = " '"
+            "#,
+        )
+        .unwrap();
+        let report = policy.report("it's a 1");
+        assert_eq!(report.len(), 3);
+        assert!(report.iter().all(|r| !r.passed));
+        assert_eq!(report[0].detail, "has 8");
+        assert_eq!(report[2].detail, "contains \" '\"");
+        let report = policy.report("'a' 'b' 'c'");
+        assert_eq!(report[2].detail, "contains \" '\"");
+    }
+
+    #[test]
+    fn invalid_policies() {
+        assert!(Policy::parse("min_length = 10\nmax_length = 8").is_err());
+        assert!(Policy::parse("min_lenght = 10").is_err());
+        assert!(Policy::parse("min_classes = 5").is_err());
+        assert!(Policy::load("/nonexistent/policy.toml").is_err());
+    }
+
+    #[test]
+    fn enforce_discards_non_compliant() {
+        let policy = Policy::parse("min_digits = 1").unwrap();
+        let attempts = std::cell::Cell::new(0);
+        let password = policy
+            .enforce(|| {
+                attempts.set(attempts.get() + 1);
+                Ok(if attempts.get() < 3 { "abc" } else { "abc1" }.to_string())
+            })
+            .unwrap();
+        assert_eq!(password, "abc1");
+        assert!(policy.enforce(|| Ok("abc".to_string())).is_err());
+    }
+}
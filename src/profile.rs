@@ -1,120 +1,281 @@
-use crate::generator::charset::CharsetBuilder;
-use crate::generator::passphrase::{self, PassphraseOptions};
-use crate::generator::random;
+use std::sync::{Arc, LazyLock, RwLock};
 
+use crate::error::{Error, Result};
+use crate::generator::Generator;
+use crate::generator::apikey::ApiKeyOptions;
+use crate::generator::charset::{Charset, CharsetBuilder};
+use crate::generator::id::{IdFormat, IdOptions};
+use crate::generator::keyboard::Layout;
+use crate::generator::mask::Mask;
+use crate::generator::passphrase::PassphraseOptions;
+use crate::generator::pronounceable::PronounceableOptions;
+use crate::generator::random::{PasswordConstraints, RandomPassword};
+use crate::generator::token::{Encoding, TokenOptions};
+use crate::rng::RandomSource;
+use crate::wifi;
+
+/// The profiles `get_profiles` and `find_profile` consult: the built-in ones
+/// and those added with [`register_profile`].
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(|| RwLock::new(Registry::builtin()));
+
+/// A named generator, as used by `pw profile`.
+#[derive(Clone)]
 pub struct Profile {
-    pub name: &'static str,
-    pub description: &'static str,
-    kind: ProfileKind,
+    pub name: String,
+    pub description: String,
+    /// Values are WPA keys: customizing must keep them valid, and they can
+    /// be shown as Wi-Fi QR codes.
+    pub wpa: bool,
+    generator: Arc<dyn Generator>,
 }
 
-enum ProfileKind {
-    Charset {
-        length: usize,
-        builder: fn() -> CharsetBuilder,
-    },
-    Passphrase(PassphraseOptions),
-    Uuid,
+impl Profile {
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        generator: impl Generator,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            wpa: false,
+            generator: Arc::new(generator),
+        }
+    }
+
+    /// Marks the values as WPA keys.
+    pub fn wpa(mut self, wpa: bool) -> Self {
+        self.wpa = wpa;
+        self
+    }
+
+    /// Overrides the length or symbol set of a character-set profile, or
+    /// keeps only the characters typed the same way on `layouts`. WPA
+    /// profiles must still produce valid WPA keys.
+    pub fn customize(
+        &mut self,
+        length: Option<usize>,
+        symbols: Option<String>,
+        layouts: Vec<Layout>,
+    ) -> Result<()> {
+        if length.is_none() && symbols.is_none() && layouts.is_empty() {
+            return Ok(());
+        }
+        let generator: &dyn std::any::Any = self.generator.as_ref();
+        let Some(random) = generator.downcast_ref::<RandomPassword>() else {
+            return Err(Error::InvalidConstraint(format!(
+                "--length, --symbols and --keyboard-safe only apply to character-set profiles, not {}",
+                self.name
+            )));
+        };
+        let random = random.customize(length, symbols, layouts)?;
+        if self.wpa {
+            wifi::check_wpa(random.length(), random.charset().chars())?;
+        }
+        self.generator = Arc::new(random);
+        Ok(())
+    }
+
+    /// The character set of a character-set profile.
+    pub fn charset(&self) -> Option<&Charset> {
+        let generator: &dyn std::any::Any = self.generator.as_ref();
+        generator
+            .downcast_ref::<RandomPassword>()
+            .map(RandomPassword::charset)
+    }
 }
 
-impl Profile {
-    pub fn generate(&self) -> String {
-        match &self.kind {
-            ProfileKind::Charset { length, builder } => {
-                let charset = builder().build();
-                random::generate_password(&charset, *length)
-            }
-            ProfileKind::Passphrase(opts) => passphrase::generate_passphrase(opts),
-            ProfileKind::Uuid => random::generate_uuid(),
+impl Generator for Profile {
+    fn generate(&self, rng: &mut dyn RandomSource) -> Result<String> {
+        self.generator.generate(rng)
+    }
+
+    fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    fn kind(&self) -> &'static str {
+        self.generator.kind()
+    }
+
+    fn alphabet(&self) -> Option<usize> {
+        self.generator.alphabet()
+    }
+
+    fn entropy(&self) -> Option<f64> {
+        self.generator.entropy()
+    }
+
+    fn entropy_is_exact(&self) -> bool {
+        self.generator.entropy_is_exact()
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.generator.is_deterministic()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        self.generator.parameters()
+    }
+}
+
+/// An ordered set of profiles with unique names.
+#[derive(Clone, Default)]
+pub struct Registry {
+    profiles: Vec<Profile>,
+}
+
+impl Registry {
+    /// The built-in profiles, in the order `pw profiles` lists them.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        for profile in builtin_profiles() {
+            registry.register(profile);
+        }
+        registry
+    }
+
+    /// Adds `profile`, replacing any profile of the same name in place.
+    pub fn register(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
         }
     }
+
+    pub fn find(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+}
+
+/// Makes `profile` available to `get_profiles` and `find_profile`, replacing
+/// a built-in or registered profile of the same name.
+pub fn register_profile(profile: Profile) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(profile);
 }
 
+/// All profiles, built-in ones first.
 pub fn get_profiles() -> Vec<Profile> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .profiles()
+        .to_vec()
+}
+
+/// The profile called `name`.
+pub fn find_profile(name: &str) -> Option<Profile> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .find(name)
+        .cloned()
+}
+
+fn charset(length: usize, builder: CharsetBuilder) -> RandomPassword {
+    RandomPassword::new(builder, length, PasswordConstraints::default())
+        .expect("built-in charset profiles are valid")
+}
+
+fn builtin_profiles() -> Vec<Profile> {
+    let digits = || {
+        CharsetBuilder::new()
+            .uppercase(false)
+            .lowercase(false)
+            .symbols(false)
+    };
     vec![
-        Profile {
-            name: "pin",
-            description: "4-digit PIN",
-            kind: ProfileKind::Charset {
-                length: 4,
-                builder: || {
-                    CharsetBuilder::new()
-                        .uppercase(false)
-                        .lowercase(false)
-                        .symbols(false)
-                },
-            },
-        },
-        Profile {
-            name: "pin6",
-            description: "6-digit PIN",
-            kind: ProfileKind::Charset {
-                length: 6,
-                builder: || {
-                    CharsetBuilder::new()
-                        .uppercase(false)
-                        .lowercase(false)
-                        .symbols(false)
-                },
-            },
-        },
-        Profile {
-            name: "wifi",
-            description: "63 chars, all sets, no ambiguous",
-            kind: ProfileKind::Charset {
-                length: 63,
-                builder: || CharsetBuilder::new().no_ambiguous(true),
+        Profile::new("pin", "4-digit PIN", charset(4, digits())),
+        Profile::new("pin6", "6-digit PIN", charset(6, digits())),
+        Profile::new(
+            "wifi",
+            "63 chars, all sets, no ambiguous",
+            charset(63, CharsetBuilder::new().no_ambiguous(true)),
+        )
+        .wpa(true),
+        Profile::new(
+            "strong",
+            "32 chars, all character sets",
+            charset(32, CharsetBuilder::new()),
+        ),
+        Profile::new(
+            "alpha",
+            "16 chars, letters only",
+            charset(16, CharsetBuilder::new().digits(false).symbols(false)),
+        ),
+        Profile::new(
+            "hex",
+            "32 hex chars",
+            charset(32, CharsetBuilder::new().hex_only(true)),
+        ),
+        Profile::new(
+            "memorable",
+            "Passphrase with 4 words",
+            PassphraseOptions::default(),
+        ),
+        Profile::new(
+            "spoken",
+            "12-char pronounceable, 2 digits",
+            PronounceableOptions {
+                digits: 2,
+                ..Default::default()
             },
-        },
-        Profile {
-            name: "strong",
-            description: "32 chars, all character sets",
-            kind: ProfileKind::Charset {
-                length: 32,
-                builder: CharsetBuilder::new,
+        ),
+        Profile::new(
+            "voucher",
+            "Voucher code, XXXX-XXXX-XXXX uppercase hex",
+            Mask::parse("?H?H?H?H-?H?H?H?H-?H?H?H?H", &Default::default())
+                .expect("built-in masks are valid"),
+        ),
+        Profile::new(
+            "secret",
+            "32 random bytes, base64url (HMAC keys, session secrets)",
+            TokenOptions {
+                bytes: 32,
+                encoding: Encoding::Base64Url,
             },
-        },
-        Profile {
-            name: "alpha",
-            description: "16 chars, letters only",
-            kind: ProfileKind::Charset {
-                length: 16,
-                builder: || CharsetBuilder::new().digits(false).symbols(false),
+        ),
+        Profile::new(
+            "apikey",
+            "32 base62 chars + CRC32 checksum (pw apikey verify)",
+            ApiKeyOptions::default(),
+        ),
+        Profile::new("uuid", "UUID v4 format", IdOptions::default()),
+        Profile::new(
+            "uuid7",
+            "UUID v7, time-ordered (database keys)",
+            IdOptions {
+                format: IdFormat::Uuid7,
+                ..Default::default()
             },
-        },
-        Profile {
-            name: "hex",
-            description: "32 hex chars",
-            kind: ProfileKind::Charset {
-                length: 32,
-                builder: || CharsetBuilder::new().hex_only(true),
+        ),
+        Profile::new(
+            "ulid",
+            "ULID, time-ordered, 26 chars",
+            IdOptions {
+                format: IdFormat::Ulid,
+                ..Default::default()
             },
-        },
-        Profile {
-            name: "memorable",
-            description: "Passphrase with 4 words",
-            kind: ProfileKind::Passphrase(PassphraseOptions::default()),
-        },
-        Profile {
-            name: "uuid",
-            description: "UUID v4 format",
-            kind: ProfileKind::Uuid,
-        },
+        ),
     ]
 }
 
-pub fn find_profile(name: &str) -> Option<Profile> {
-    get_profiles().into_iter().find(|p| p.name == name)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generator::apikey;
 
     #[test]
     fn pin_profile_is_4_digits() {
         let profile = find_profile("pin").unwrap();
-        let pw = profile.generate();
+        let pw = profile.generate(&mut rand::rng()).unwrap();
         assert_eq!(pw.len(), 4);
         assert!(pw.chars().all(|c| c.is_ascii_digit()));
     }
@@ -122,15 +283,24 @@ mod tests {
     #[test]
     fn pin6_profile_is_6_digits() {
         let profile = find_profile("pin6").unwrap();
-        let pw = profile.generate();
+        let pw = profile.generate(&mut rand::rng()).unwrap();
         assert_eq!(pw.len(), 6);
         assert!(pw.chars().all(|c| c.is_ascii_digit()));
     }
 
+    #[test]
+    fn builtin_charsets_have_no_duplicates() {
+        for profile in get_profiles() {
+            if let Some(charset) = profile.charset() {
+                assert!(charset.duplicates().is_empty(), "{}", profile.name);
+            }
+        }
+    }
+
     #[test]
     fn hex_profile_is_32_hex() {
         let profile = find_profile("hex").unwrap();
-        let pw = profile.generate();
+        let pw = profile.generate(&mut rand::rng()).unwrap();
         assert_eq!(pw.len(), 32);
         assert!(pw.chars().all(|c| c.is_ascii_hexdigit()));
     }
@@ -138,11 +308,82 @@ mod tests {
     #[test]
     fn uuid_profile_format() {
         let profile = find_profile("uuid").unwrap();
-        let pw = profile.generate();
+        let pw = profile.generate(&mut rand::rng()).unwrap();
         assert_eq!(pw.len(), 36);
         assert_eq!(pw.matches('-').count(), 4);
     }
 
+    #[test]
+    fn voucher_profile_format() {
+        let profile = find_profile("voucher").unwrap();
+        let pw = profile.generate(&mut rand::rng()).unwrap();
+        assert_eq!(pw.len(), 14);
+        assert_eq!(pw.matches('-').count(), 2);
+        assert_eq!(profile.entropy(), Some(48.0));
+    }
+
+    #[test]
+    fn spoken_profile_format() {
+        let profile = find_profile("spoken").unwrap();
+        let pw = profile.generate(&mut rand::rng()).unwrap();
+        assert_eq!(pw.len(), 12);
+        assert_eq!(pw.chars().filter(|c| c.is_ascii_digit()).count(), 2);
+    }
+
+    #[test]
+    fn secret_profile_is_256_bits() {
+        let profile = find_profile("secret").unwrap();
+        let pw = profile.generate(&mut rand::rng()).unwrap();
+        assert_eq!(pw.len(), 43);
+        assert_eq!(profile.entropy(), Some(256.0));
+    }
+
+    #[test]
+    fn apikey_profile_verifies() {
+        let profile = find_profile("apikey").unwrap();
+        let key = profile.generate(&mut rand::rng()).unwrap();
+        assert_eq!(key.len(), 38);
+        assert!(apikey::verify_api_key(&key, None, apikey::Checksum::Crc32).is_ok());
+    }
+
+    #[test]
+    fn wifi_customization_stays_wpa() {
+        let mut profile = find_profile("wifi").unwrap();
+        profile
+            .customize(Some(20), Some("!#%".to_string()), Vec::new())
+            .unwrap();
+        let key = profile.generate(&mut rand::rng()).unwrap();
+        assert_eq!(key.len(), 20);
+        assert!(wifi::check_wpa_key(&key).is_ok());
+
+        // The checks follow the profile under another name
+        let mut profile = find_profile("wifi").unwrap();
+        profile.name = "guest".to_string();
+        assert!(matches!(
+            profile.customize(Some(64), None, Vec::new()),
+            Err(Error::InvalidWifi(_))
+        ));
+        assert!(matches!(
+            profile.customize(None, Some("€".to_string()), Vec::new()),
+            Err(Error::InvalidWifi(_))
+        ));
+        let mut profile = find_profile("memorable").unwrap();
+        assert!(profile.customize(Some(20), None, Vec::new()).is_err());
+        assert!(
+            profile
+                .customize(None, None, vec![Layout::Qwerty, Layout::Azerty])
+                .is_err()
+        );
+
+        let mut profile = find_profile("wifi").unwrap();
+        profile
+            .customize(None, None, vec![Layout::Qwerty, Layout::Azerty])
+            .unwrap();
+        let key = profile.generate(&mut rand::rng()).unwrap();
+        assert!(!key.contains(['a', 'q', 'z', 'w', 'm', '1']));
+        assert!(wifi::check_wpa_key(&key).is_ok());
+    }
+
     #[test]
     fn all_profiles_exist() {
         let names = [
@@ -153,13 +394,72 @@ mod tests {
             "alpha",
             "hex",
             "memorable",
+            "spoken",
+            "voucher",
+            "secret",
+            "apikey",
             "uuid",
+            "uuid7",
+            "ulid",
         ];
         for name in names {
             assert!(find_profile(name).is_some(), "Profile '{name}' not found");
         }
     }
 
+    struct Fixed;
+
+    impl Generator for Fixed {
+        fn generate(&self, _rng: &mut dyn RandomSource) -> Result<String> {
+            Ok("fixed".to_string())
+        }
+
+        fn describe(&self) -> String {
+            "Always the same".to_string()
+        }
+
+        fn kind(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn entropy(&self) -> Option<f64> {
+            Some(0.0)
+        }
+
+        fn parameters(&self) -> Vec<(&'static str, String)> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn register_replaces_by_name() {
+        let mut registry = Registry::builtin();
+        let count = registry.profiles().len();
+        registry.register(Profile::new("pin", "Fixed PIN", Fixed));
+        registry.register(Profile::new("fixed", "Fixed", Fixed));
+        assert_eq!(registry.profiles().len(), count + 1);
+        assert_eq!(registry.profiles()[0].name, "pin");
+        let pin = registry.find("pin").unwrap();
+        assert_eq!(pin.generate(&mut rand::rng()).unwrap(), "fixed");
+        assert_eq!(registry.profiles().last().unwrap().name, "fixed");
+    }
+
+    #[test]
+    fn registered_profiles_are_found() {
+        register_profile(Profile::new("test-fixed", "Fixed", Fixed));
+        let mut profile = find_profile("test-fixed").unwrap();
+        assert_eq!(profile.generate(&mut rand::rng()).unwrap(), "fixed");
+        assert!(get_profiles().iter().any(|p| p.name == "test-fixed"));
+        assert!(profile.customize(Some(8), None, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn describes_parameters() {
+        let profile = find_profile("strong").unwrap();
+        assert!(profile.parameters().contains(&("length", "32".to_string())));
+        assert_eq!(profile.describe(), "32 chars, all character sets");
+    }
+
     #[test]
     fn unknown_profile_returns_none() {
         assert!(find_profile("nonexistent").is_none());
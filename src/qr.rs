@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use qrcodegen::{QrCode, QrCodeEcc};
+
+use crate::error::{Error, Result};
+use crate::file;
+
+/// Light modules around the symbol, as ISO/IEC 18004 requires.
+const QUIET_ZONE: i32 = 4;
+/// Pixels per module in PNG files.
+const PNG_SCALE: i32 = 8;
+
+/// Encodes `text` with medium (15%) error correction.
+pub fn encode(text: &str) -> Result<QrCode> {
+    QrCode::encode_text(text, QrCodeEcc::Medium)
+        .map_err(|e| Error::QrCode(format!("payload does not fit: {e}")))
+}
+
+/// Whether the module at `(x, y)` is dark, counting the quiet zone.
+fn dark(qr: &QrCode, x: i32, y: i32) -> bool {
+    // get_module is light outside the symbol
+    qr.get_module(x - QUIET_ZONE, y - QUIET_ZONE)
+}
+
+fn width(qr: &QrCode) -> i32 {
+    qr.size() + 2 * QUIET_ZONE
+}
+
+/// Renders two rows of modules per line of text with Unicode half blocks,
+/// dark modules being drawn.
+pub fn render_terminal(qr: &QrCode) -> Vec<String> {
+    let width = width(qr);
+    (0..width)
+        .step_by(2)
+        .map(|y| {
+            (0..width)
+                .map(|x| match (dark(qr, x, y), dark(qr, x, y + 1)) {
+                    (true, true) => '\u{2588}',
+                    (true, false) => '\u{2580}',
+                    (false, true) => '\u{2584}',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// An SVG document with one unit per module.
+pub fn to_svg(qr: &QrCode) -> String {
+    let width = width(qr);
+    let mut path = String::new();
+    for y in 0..width {
+        for x in 0..width {
+            if dark(qr, x, y) {
+                path.push_str(&format!("M{x},{y}h1v1h-1z"));
+            }
+        }
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {width}\" \
+         shape-rendering=\"crispEdges\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\n\
+         <path d=\"{path}\" fill=\"#000\"/>\n\
+         </svg>\n"
+    )
+}
+
+/// An 8-bit grayscale PNG with [`PNG_SCALE`] pixels per module.
+pub fn to_png(qr: &QrCode) -> Result<Vec<u8>> {
+    let side = width(qr) * PNG_SCALE;
+    let pixels: Vec<u8> = (0..side)
+        .flat_map(|y| {
+            (0..side).map(move |x| {
+                if dark(qr, x / PNG_SCALE, y / PNG_SCALE) {
+                    0
+                } else {
+                    255
+                }
+            })
+        })
+        .collect();
+
+    let fail = |e: png::EncodingError| Error::QrCode(format!("cannot encode PNG: {e}"));
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(fail)?;
+    writer.write_image_data(&pixels).map_err(fail)?;
+    writer.finish().map_err(fail)?;
+    Ok(png)
+}
+
+/// Writes the code to a PNG or SVG file, chosen by the extension of `path`.
+/// The code holds a secret, so the file is only readable by its owner and
+/// an existing file is only replaced with `overwrite`.
+pub fn write_image(qr: &QrCode, path: &Path, overwrite: bool) -> Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let contents = match extension.as_deref() {
+        Some("png") => to_png(qr)?,
+        Some("svg") => to_svg(qr).into_bytes(),
+        _ => {
+            return Err(Error::QrCode(format!(
+                "{} is neither a .png nor a .svg file",
+                path.display()
+            )));
+        }
+    };
+
+    file::write_secret(path, &contents, overwrite)
+        .map_err(|e| Error::QrCode(format!("{}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code() -> QrCode {
+        encode("WIFI:T:WPA;S:Home;P:correct horse;;").unwrap()
+    }
+
+    #[test]
+    fn terminal_has_two_rows_per_line() {
+        let qr = code();
+        let lines = render_terminal(&qr);
+        let width = (qr.size() + 8) as usize;
+        assert_eq!(lines.len(), width.div_ceil(2));
+        assert!(lines.iter().all(|l| l.chars().count() == width));
+        // Two lines of quiet zone, then the top edge of the finder pattern
+        assert!(lines[..2].iter().all(|l| l.chars().all(|c| c == ' ')));
+        assert_eq!(lines[2].chars().nth(4), Some('\u{2588}'));
+        assert_eq!(lines[2].chars().nth(5), Some('\u{2580}'));
+    }
+
+    #[test]
+    fn svg_draws_dark_modules() {
+        let qr = code();
+        let svg = to_svg(&qr);
+        let width = qr.size() + 8;
+        assert!(svg.contains(&format!("viewBox=\"0 0 {width} {width}\"")));
+        // Top-left corner of the finder pattern
+        assert!(svg.contains("M4,4h1v1h-1z"));
+        assert!(!svg.contains("M0,0h1v1h-1z"));
+    }
+
+    #[test]
+    fn png_is_scaled() {
+        let qr = code();
+        let png = to_png(&qr).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(png));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        let side = ((qr.size() + 8) * PNG_SCALE) as usize;
+        assert_eq!((info.width as usize, info.height as usize), (side, side));
+        assert_eq!(pixels[0], 255);
+        let corner = 4 * PNG_SCALE as usize;
+        assert_eq!(pixels[corner * side + corner], 0);
+    }
+
+    #[test]
+    fn oversized_payload() {
+        assert!(matches!(encode(&"x".repeat(3000)), Err(Error::QrCode(_))));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::error::{Error, Result};
+
+/// Characters with a meaning in `WIFI:` payloads, escaped with `\`.
+const SPECIAL: &[char] = &['\\', ';', ',', ':', '"'];
+/// WPA2/WPA3 passphrase length limits (IEEE 802.11i, annex H.4).
+const WPA_MIN: usize = 8;
+const WPA_MAX: usize = 63;
+/// SSIDs are at most 32 bytes.
+const SSID_MAX: usize = 32;
+
+/// The `WIFI:` payload that phone cameras join a WPA network from, as
+/// defined by the ZXing barcode contents conventions.
+pub fn payload(ssid: &str, key: &str) -> Result<String> {
+    if ssid.is_empty() {
+        return Err(Error::InvalidWifi("SSID is empty".to_string()));
+    }
+    if ssid.len() > SSID_MAX {
+        return Err(Error::InvalidWifi(format!(
+            "SSID is {} bytes, at most {SSID_MAX} are allowed",
+            ssid.len()
+        )));
+    }
+    check_wpa_key(key)?;
+    Ok(format!("WIFI:T:WPA;S:{};P:{};;", escape(ssid), escape(key)))
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub fn check_wpa_key(key: &str) -> Result<()> {
+    check_wpa(key.chars().count(), &key.chars().collect::<Vec<_>>())
+}
+
+/// Checks that keys of `length` characters drawn from `chars` are valid WPA
+/// passphrases: 8 to 63 printable ASCII characters.
+pub fn check_wpa(length: usize, chars: &[char]) -> Result<()> {
+    if !(WPA_MIN..=WPA_MAX).contains(&length) {
+        return Err(Error::InvalidWifi(format!(
+            "WPA keys are {WPA_MIN} to {WPA_MAX} characters, got {length}"
+        )));
+    }
+    if let Some(c) = chars.iter().find(|c| !(' '..='~').contains(*c)) {
+        return Err(Error::InvalidWifi(format!(
+            "WPA keys are printable ASCII, {c:?} is not"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_escapes_special_characters() {
+        assert_eq!(
+            payload("Home", "correct horse").unwrap(),
+            "WIFI:T:WPA;S:Home;P:correct horse;;"
+        );
+        assert_eq!(
+            payload(r#"Caf\;, "1:2""#, r#"a;b,c:d\e"f"#).unwrap(),
+            r#"WIFI:T:WPA;S:Caf\\\;\, \"1\:2\";P:a\;b\,c\:d\\e\"f;;"#
+        );
+    }
+
+    #[test]
+    fn invalid_networks() {
+        assert!(payload("", "password1").is_err());
+        assert!(payload(&"x".repeat(33), "password1").is_err());
+        assert!(payload("Home", "short").is_err());
+        assert!(payload("Home", &"x".repeat(64)).is_err());
+        assert!(payload("Home", "pässword1").is_err());
+        assert!(payload("Home", &"x".repeat(63)).is_ok());
+    }
+
+    #[test]
+    fn wpa_alphabets() {
+        let ascii: Vec<char> = (' '..='~').collect();
+        assert_eq!(check_wpa(8, &ascii), Ok(()));
+        assert!(check_wpa(7, &ascii).is_err());
+        assert!(check_wpa(20, &['a', '€']).is_err());
+        assert!(check_wpa(20, &['a', '\t']).is_err());
+    }
+}